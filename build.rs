@@ -0,0 +1,50 @@
+use std::process::Command;
+
+/// what `--version`, the startup log and /healthz say of the build, through
+/// `env!` in src/version.rs
+fn main() {
+  println!("cargo:rerun-if-env-changed=GIT_COMMIT");
+  // builds from a tarball have no git to ask
+  let commit = std::env::var("GIT_COMMIT")
+    .ok()
+    .filter(|it| !it.is_empty())
+    .or_else(git_commit)
+    .unwrap_or_else(|| "unknown".to_string());
+  println!("cargo:rustc-env=BUILD_COMMIT={commit}");
+
+  let target = std::env::var("TARGET").unwrap_or_default();
+  println!("cargo:rustc-env=BUILD_TARGET={target}");
+
+  let mut features = std::env::vars()
+    .filter_map(|(name, _)| {
+      let feature = name.strip_prefix("CARGO_FEATURE_")?;
+      Some(feature.to_ascii_lowercase().replace('_', "-"))
+    })
+    .filter(|it| it != "default")
+    .collect::<Vec<_>>();
+  features.sort();
+  println!("cargo:rustc-env=BUILD_FEATURES={}", features.join(","));
+}
+
+/// the short hash of HEAD, `-dirty` when there are uncommitted changes
+fn git_commit() -> Option<String> {
+  for path in [".git/HEAD", ".git/index", "src"] {
+    if std::path::Path::new(path).exists() {
+      println!("cargo:rerun-if-changed={path}");
+    }
+  }
+  let git = |args: &[&str]| {
+    let output = Command::new("git").args(args).output().ok()?;
+    output
+      .status
+      .success()
+      .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+  };
+  let commit = git(&["rev-parse", "--short=12", "HEAD"])?;
+  let dirty =
+    git(&["status", "--porcelain", "--untracked-files=no"]).is_some_and(|it| !it.is_empty());
+  Some(match dirty {
+    true => format!("{commit}-dirty"),
+    false => commit,
+  })
+}
@@ -0,0 +1,183 @@
+//! BililiveRecorder doesn't sign or authenticate its webhooks, it posts the
+//! bare event json with a `BililiveRecorder/<version>` user agent and nothing
+//! else. With it the token goes in the webhook url as `?token=`, the other
+//! schemes are for proxies in front of this or other tools that set headers.
+
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use base64::Engine;
+use ring::hmac;
+
+/// how requests prove they know `--token`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecorderAuth {
+  /// `?token=<token>` or `Authorization: Bearer <token>`
+  #[default]
+  Token,
+  /// `Authorization: Basic`, with the token being `user:password`
+  Basic,
+  /// webhooks carry `X-Signature-256: sha256=<hex hmac-sha256 of the body>`
+  /// keyed by the token, other endpoints take the token like [`Self::Token`]
+  Hmac,
+}
+
+impl FromStr for RecorderAuth {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "token" => Ok(Self::Token),
+      "basic" => Ok(Self::Basic),
+      "hmac" => Ok(Self::Hmac),
+      _ => Err(format!(
+        "unknown auth scheme `{s}`, expected token, basic or hmac"
+      )),
+    }
+  }
+}
+
+/// an address or a CIDR range of them, like `192.168.1.0/24` or `::1`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpRange {
+  addr: IpAddr,
+  prefix: u8,
+}
+
+impl FromStr for IpRange {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let (addr, prefix) = match s.split_once('/') {
+      Some((addr, prefix)) => (addr, Some(prefix)),
+      None => (s, None),
+    };
+    let addr = IpAddr::from_str(addr.trim()).map_err(|_| format!("invalid address `{s}`"))?;
+    let bits = match addr {
+      IpAddr::V4(_) => 32,
+      IpAddr::V6(_) => 128,
+    };
+    let prefix = match prefix {
+      Some(it) => u8::from_str(it.trim())
+        .ok()
+        .filter(|it| *it <= bits)
+        .ok_or_else(|| format!("invalid prefix length in `{s}`, expected 0 to {bits}"))?,
+      None => bits,
+    };
+    Ok(Self { addr, prefix })
+  }
+}
+
+impl IpRange {
+  /// ipv4 clients of a dual stack listener count as their ipv4 address
+  pub fn contains(&self, ip: IpAddr) -> bool {
+    let prefix = u32::from(self.prefix);
+    match (self.addr, ip.to_canonical()) {
+      (IpAddr::V4(range), IpAddr::V4(ip)) => {
+        let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+        u32::from(range) & mask == u32::from(ip) & mask
+      }
+      (IpAddr::V6(range), IpAddr::V6(ip)) => {
+        let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+        u128::from(range) & mask == u128::from(ip) & mask
+      }
+      _ => false,
+    }
+  }
+}
+
+/// the header [`RecorderAuth::Hmac`] signatures are in
+pub static SIGNATURE_HEADER: &str = "X-Signature-256";
+
+pub fn token_matches(token: &str, query: Option<&str>, authorization: Option<&str>) -> bool {
+  let from_query = query
+    .into_iter()
+    .flat_map(|it| form_urlencoded::parse(it.as_bytes()))
+    .any(|(key, value)| key == "token" && constant_time_eq(value.as_bytes(), token.as_bytes()));
+  let from_header = authorization
+    .and_then(|it| it.strip_prefix("Bearer "))
+    .is_some_and(|it| constant_time_eq(it.as_bytes(), token.as_bytes()));
+  from_query || from_header
+}
+
+pub fn basic_matches(token: &str, authorization: Option<&str>) -> bool {
+  authorization
+    .and_then(|it| it.strip_prefix("Basic "))
+    .and_then(|it| {
+      base64::engine::general_purpose::STANDARD
+        .decode(it.trim())
+        .ok()
+    })
+    .is_some_and(|it| constant_time_eq(&it, token.as_bytes()))
+}
+
+/// takes as long for any two values of a length, so a guess doesn't tell how
+/// much of it was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+  a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+pub fn signature_matches(token: &str, signature: Option<&str>, body: &[u8]) -> bool {
+  let Some(signature) = signature
+    .and_then(|it| it.strip_prefix("sha256="))
+    .and_then(|it| hex::decode(it.trim()).ok())
+  else {
+    return false;
+  };
+  let key = hmac::Key::new(hmac::HMAC_SHA256, token.as_bytes());
+  hmac::verify(&key, body, &signature).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn query_token_is_decoded() {
+    let token = "a+b%c&d";
+    assert!(token_matches(token, Some("token=a%2Bb%25c%26d"), None));
+    assert!(token_matches(token, Some("x=1&token=a%2Bb%25c%26d"), None));
+    // undecoded, `+` is a space and `&` ends the value
+    assert!(!token_matches(token, Some("token=a+b%c&d"), None));
+    assert!(token_matches("a b", Some("token=a+b"), None));
+  }
+
+  #[test]
+  fn tokens_have_to_match_whole() {
+    assert!(token_matches("secret", None, Some("Bearer secret")));
+    assert!(!token_matches("secret", None, Some("Bearer secre")));
+    assert!(!token_matches("secret", None, Some("Bearer secrets")));
+    assert!(!token_matches("secret", Some("token="), None));
+    assert!(!token_matches("secret", None, None));
+  }
+
+  #[test]
+  fn basic_credentials() {
+    // user:pass
+    assert!(basic_matches("user:pass", Some("Basic dXNlcjpwYXNz")));
+    assert!(!basic_matches("user:pasS", Some("Basic dXNlcjpwYXNz")));
+  }
+
+  #[test]
+  fn ip_ranges() {
+    let range = IpRange::from_str("192.168.1.0/24").unwrap();
+    assert!(range.contains("192.168.1.42".parse().unwrap()));
+    assert!(!range.contains("192.168.2.1".parse().unwrap()));
+    // from a dual stack listener
+    assert!(range.contains("::ffff:192.168.1.42".parse().unwrap()));
+    assert!(!range.contains("fe80::1".parse().unwrap()));
+
+    let range = IpRange::from_str("fd00::/8").unwrap();
+    assert!(range.contains("fd12::1".parse().unwrap()));
+    assert!(!range.contains("fe80::1".parse().unwrap()));
+
+    let any = IpRange::from_str("0.0.0.0/0").unwrap();
+    assert!(any.contains("8.8.8.8".parse().unwrap()));
+    let single = IpRange::from_str("::1").unwrap();
+    assert!(single.contains("::1".parse().unwrap()));
+    assert!(!single.contains("::2".parse().unwrap()));
+
+    assert!(IpRange::from_str("10.0.0.0/33").is_err());
+    assert!(IpRange::from_str("10.0.0/8").is_err());
+  }
+}
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::filter::RoomFilter;
+use crate::forward::ForwardConfig;
+use crate::{notifier, template};
+
+/// a `--config`, one starting with `?` is skipped when it doesn't exist
+#[derive(Debug, Clone)]
+pub struct ConfigPath {
+  pub path: PathBuf,
+  pub optional: bool,
+}
+
+impl FromStr for ConfigPath {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let (path, optional) = match s.strip_prefix('?') {
+      Some(path) => (path, true),
+      None => (s, false),
+    };
+    if path.is_empty() {
+      return Err("empty config path".to_string());
+    }
+    Ok(Self {
+      path: PathBuf::from(path),
+      optional,
+    })
+  }
+}
+
+/// settings that don't fit on the command line, read from `--config`
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+  /// templates keyed by event type, e.g. `[templates.StreamStarted]`
+  #[serde(default)]
+  pub templates: HashMap<String, TemplateConfig>,
+  /// settings of each notifier by name, e.g. `[notifiers.ntfy]`
+  #[serde(default)]
+  pub notifiers: HashMap<String, NotifierConfig>,
+  /// settings of each `--forward-url`, e.g. `[forward."https://example.com/hook"]`
+  #[serde(default)]
+  pub forward: HashMap<String, ForwardConfig>,
+}
+
+#[derive(Deserialize, Default, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct NotifierConfig {
+  /// only send these rooms through the notifier, on top of --roomid-filter
+  pub rooms: Option<RoomFilter>,
+  /// only send these event types through the notifier, of --notify-events,
+  /// notifications not about an event always go through
+  pub events: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateConfig {
+  pub summary: Option<String>,
+  pub body: Option<String>,
+}
+
+impl Config {
+  /// the files merged in order, see [`Self::merge`]
+  pub fn load_all(paths: &[ConfigPath]) -> Result<Self, String> {
+    let mut config = Self::default();
+    for it in paths {
+      if it.optional && !it.path.exists() {
+        continue;
+      }
+      config.merge(Self::load(&it.path)?);
+    }
+    Ok(config)
+  }
+
+  /// `other` over this one table by table, a template, notifier or forward
+  /// target set in both gets the fields `other` sets, forward headers are
+  /// merged by name, anything else, lists included, is replaced as a whole
+  pub fn merge(&mut self, other: Self) {
+    for (event_type, template) in other.templates {
+      let merged = self.templates.entry(event_type).or_default();
+      merged.summary = template.summary.or(merged.summary.take());
+      merged.body = template.body.or(merged.body.take());
+    }
+    for (name, notifier) in other.notifiers {
+      let merged = self.notifiers.entry(name).or_default();
+      merged.rooms = notifier.rooms.or(merged.rooms.take());
+      merged.events = notifier.events.or(merged.events.take());
+    }
+    for (url, forward) in other.forward {
+      let merged = self.forward.entry(url).or_default();
+      merged.template = forward.template.or(merged.template.take());
+      merged.headers.extend(forward.headers);
+      merged.timeout_secs = forward.timeout_secs.or(merged.timeout_secs);
+    }
+  }
+
+  pub fn load(path: &Path) -> Result<Self, String> {
+    let src = std::fs::read_to_string(path)
+      .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+    let config: Self =
+      toml::from_str(&src).map_err(|err| format!("failed to parse {}: {err}", path.display()))?;
+    if let Some(name) = config
+      .notifiers
+      .keys()
+      .find(|it| !notifier::NAMES.contains(&it.as_str()))
+    {
+      return Err(format!(
+        "unknown notifier `{name}` in {}, expected one of {}",
+        path.display(),
+        notifier::NAMES.join(", ")
+      ));
+    }
+    if let Some(event) = config
+      .notifiers
+      .values()
+      .flat_map(|it| it.events.iter().flatten())
+      .find(|it| !template::EVENT_TYPES.contains(&it.as_str()))
+    {
+      return Err(format!(
+        "unknown event type `{event}` in {}",
+        path.display()
+      ));
+    }
+    Ok(config)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// in a temp dir of its own for this process
+  fn write(name: &str, src: &str) -> ConfigPath {
+    let dir = std::env::temp_dir().join(format!("bilibili_rec_notifier_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, src).unwrap();
+    ConfigPath {
+      path,
+      optional: false,
+    }
+  }
+
+  #[test]
+  fn later_files_win() {
+    let base = write(
+      "base.toml",
+      r#"
+        [templates.StreamStarted]
+        summary = "base"
+        body = "base body"
+
+        [notifiers.ntfy]
+        rooms = "1-10"
+        events = ["StreamStarted", "StreamEnded"]
+
+        [forward."https://example.com/hook"]
+        headers = { A = "base", B = "base" }
+        timeout_secs = 5
+      "#,
+    );
+    let local = write(
+      "local.toml",
+      r#"
+        [templates.StreamStarted]
+        summary = "local"
+
+        [notifiers.ntfy]
+        events = ["StreamEnded"]
+
+        [forward."https://example.com/hook"]
+        headers = { B = "local" }
+      "#,
+    );
+    let missing = ConfigPath::from_str("?/nonexistent/bilibili_rec_notifier.toml").unwrap();
+
+    let config = Config::load_all(&[base.clone(), missing, local.clone()]).unwrap();
+    let template = &config.templates["StreamStarted"];
+    assert_eq!(template.summary.as_deref(), Some("local"));
+    assert_eq!(template.body.as_deref(), Some("base body"));
+    let ntfy = &config.notifiers["ntfy"];
+    assert_eq!(ntfy.rooms, Some(RoomFilter::from_str("1-10").unwrap()));
+    // lists are replaced, not appended to
+    assert_eq!(
+      ntfy.events.as_deref(),
+      Some(&["StreamEnded".to_string()][..])
+    );
+    let forward = &config.forward["https://example.com/hook"];
+    assert_eq!(forward.headers["A"], "base");
+    assert_eq!(forward.headers["B"], "local");
+    assert_eq!(forward.timeout_secs, Some(5));
+
+    // the other way around
+    let config = Config::load_all(&[local, base]).unwrap();
+    assert_eq!(
+      config.templates["StreamStarted"].summary.as_deref(),
+      Some("base")
+    );
+    assert_eq!(
+      config.forward["https://example.com/hook"].headers["B"],
+      "base"
+    );
+  }
+
+  #[test]
+  fn missing_files_fail_unless_optional() {
+    let missing = ConfigPath::from_str("/nonexistent/bilibili_rec_notifier.toml").unwrap();
+    assert!(Config::load_all(&[missing]).is_err());
+    let optional = ConfigPath::from_str("?/nonexistent/bilibili_rec_notifier.toml").unwrap();
+    assert!(Config::load_all(&[optional]).is_ok());
+  }
+}
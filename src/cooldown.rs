@@ -0,0 +1,35 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// at most one stream start notification per room within `window`
+pub struct Cooldown {
+  pub window: Duration,
+  /// called off by the stream ending
+  pub reset_on_end: bool,
+  pub last_notified: Mutex<HashMap<i64, Instant>>,
+}
+
+impl Cooldown {
+  /// whether the room may be notified now, if so the window starts over
+  pub fn try_start(&self, room_id: i64) -> bool {
+    if self.window.is_zero() {
+      return true;
+    }
+
+    let mut last_notified = self.last_notified.lock().unwrap();
+    let now = Instant::now();
+    last_notified.retain(|_, it| now.duration_since(*it) < self.window);
+    if last_notified.contains_key(&room_id) {
+      return false;
+    }
+    last_notified.insert(room_id, now);
+    true
+  }
+
+  pub fn end(&self, room_id: i64) {
+    if self.reset_on_end {
+      self.last_notified.lock().unwrap().remove(&room_id);
+    }
+  }
+}
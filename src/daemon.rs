@@ -0,0 +1,145 @@
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use tracing::{info, warn};
+
+/// --pid-file, from when it's found free
+static PID_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// --pid-file, once written, to remove on exit
+static WRITTEN: OnceLock<PathBuf> = OnceLock::new();
+
+/// what the child of [`fork`] has left to do once it's [`ready`]
+static DETACH: Mutex<Option<Detach>> = Mutex::new(None);
+
+struct Detach {
+  /// to the parent, which exits once a byte comes through
+  ready: File,
+  /// --log-file
+  log_file: Option<PathBuf>,
+}
+
+/// forks before anything else runs, the parent only waits for the child to be
+/// [`ready`] and exits then, or as the child did when it exits first, having
+/// said why on the terminal they share until then
+pub fn fork(log_file: Option<PathBuf>) -> Result<(), String> {
+  let mut fds = [0; 2];
+  if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+    let err = std::io::Error::last_os_error();
+    return Err(format!("failed to fork into the background\n{err}"));
+  }
+  // the players and commands it runs have no use for them
+  for fd in fds {
+    unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+  }
+  let (mut read, ready) = unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+
+  match unsafe { libc::fork() } {
+    -1 => {
+      let err = std::io::Error::last_os_error();
+      Err(format!("failed to fork into the background\n{err}"))
+    }
+    0 => {
+      drop(read);
+      // no longer hung up on with the terminal
+      unsafe { libc::setsid() };
+      *DETACH.lock().unwrap() = Some(Detach { ready, log_file });
+      Ok(())
+    }
+    pid => {
+      drop(ready);
+      let mut byte = [0; 1];
+      if matches!(read.read(&mut byte), Ok(1)) {
+        println!("running in the background as pid {pid}");
+        std::process::exit(0);
+      }
+      let mut status = 0;
+      unsafe { libc::waitpid(pid, &mut status, 0) };
+      match libc::WIFEXITED(status) {
+        true => std::process::exit(libc::WEXITSTATUS(status)),
+        false => std::process::exit(1),
+      }
+    }
+  }
+}
+
+/// takes --pid-file for this process, unless the one it names is still
+/// running, a file left behind by one that isn't is taken over
+pub fn claim_pid_file(path: &Path) -> Result<(), String> {
+  match std::fs::read_to_string(path) {
+    Ok(pid) => match pid.trim().parse::<libc::pid_t>() {
+      Ok(pid) if running(pid) => {
+        return Err(format!(
+          "already running as pid {pid}, going by {}",
+          path.display()
+        ));
+      }
+      _ => warn!(
+        "{} is stale, pid {} isn't running, taking it over",
+        path.display(),
+        pid.trim()
+      ),
+    },
+    Err(err) if err.kind() == ErrorKind::NotFound => {}
+    Err(err) => return Err(format!("failed to read {}\n{err}", path.display())),
+  }
+  let _ = PID_FILE.set(path.to_path_buf());
+  Ok(())
+}
+
+fn running(pid: libc::pid_t) -> bool {
+  if pid <= 0 {
+    return false;
+  }
+  // signal 0 only checks for the process, EPERM is one of another user's
+  let alive = unsafe { libc::kill(pid, 0) } == 0;
+  alive || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// once the listeners are bound, writes --pid-file, and with --daemon moves
+/// stdin, stdout and stderr off the terminal and lets the parent exit
+pub fn ready() -> Result<(), String> {
+  if let Some(path) = PID_FILE.get() {
+    std::fs::write(path, format!("{}\n", std::process::id()))
+      .map_err(|err| format!("failed to write {}\n{err}", path.display()))?;
+    let _ = WRITTEN.set(path.clone());
+  }
+
+  let Some(detach) = DETACH.lock().unwrap().take() else {
+    return Ok(());
+  };
+  let output = match &detach.log_file {
+    Some(path) => OpenOptions::new()
+      .create(true)
+      .append(true)
+      .open(path)
+      .map_err(|err| format!("failed to open {}\n{err}", path.display()))?,
+    None => OpenOptions::new()
+      .write(true)
+      .open("/dev/null")
+      .map_err(|err| format!("failed to open /dev/null\n{err}"))?,
+  };
+  let input = File::open("/dev/null").map_err(|err| format!("failed to open /dev/null\n{err}"))?;
+  unsafe {
+    libc::dup2(input.as_raw_fd(), libc::STDIN_FILENO);
+    libc::dup2(output.as_raw_fd(), libc::STDOUT_FILENO);
+    libc::dup2(output.as_raw_fd(), libc::STDERR_FILENO);
+  }
+  let mut ready = detach.ready;
+  let _ = ready.write_all(b"\n");
+  info!("in the background as pid {}", std::process::id());
+  Ok(())
+}
+
+/// on a clean exit, what [`ready`] wrote is gone again
+pub fn remove_pid_file() {
+  let Some(path) = WRITTEN.get() else {
+    return;
+  };
+  if let Err(err) = std::fs::remove_file(path) {
+    warn!("failed to remove {}\n{err}", path.display());
+  }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::future::join_all;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// room id to the id and task of its pending action
+type Pending = Arc<Mutex<HashMap<i64, (u64, JoinHandle<()>)>>>;
+
+/// delays a per room action, so it can be called off within the window
+pub struct Debouncer {
+  window: Duration,
+  next_id: AtomicU64,
+  pending: Pending,
+  /// set by [`Self::flush`], the pending actions run right away then
+  flushing: watch::Sender<bool>,
+}
+
+impl Debouncer {
+  pub fn new(window: Duration) -> Self {
+    Self {
+      window,
+      next_id: AtomicU64::new(0),
+      pending: Default::default(),
+      flushing: watch::channel(false).0,
+    }
+  }
+
+  pub fn enabled(&self) -> bool {
+    !self.window.is_zero()
+  }
+
+  /// run `action` once the window passes, replacing one still pending for the room
+  pub fn schedule<F>(&self, room_id: i64, action: F)
+  where
+    F: Future<Output = ()> + Send + 'static,
+  {
+    let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+    let window = self.window;
+    let pending = self.pending.clone();
+    let mut flushing = self.flushing.subscribe();
+
+    let mut guard = self.pending.lock().unwrap();
+    let task = tokio::spawn(async move {
+      if !*flushing.borrow() {
+        tokio::select! {
+          _ = tokio::time::sleep(window) => {}
+          _ = flushing.changed() => {}
+        }
+      }
+      {
+        let mut pending = pending.lock().unwrap();
+        match pending.get(&room_id) {
+          Some((pending_id, _)) if *pending_id == id => {
+            pending.remove(&room_id);
+          }
+          // taken by the flush
+          None if *flushing.borrow() => {}
+          _ => return,
+        };
+      }
+      action.await;
+    });
+    if let Some((_, previous)) = guard.insert(room_id, (id, task)) {
+      previous.abort();
+    }
+  }
+
+  /// call off the pending action of the room, returns whether there was one
+  pub fn cancel(&self, room_id: i64) -> bool {
+    match self.pending.lock().unwrap().remove(&room_id) {
+      Some((_, task)) => {
+        task.abort();
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// run the pending actions now instead of once their window passes, and wait
+  /// for them, before exiting
+  pub async fn flush(&self) {
+    self.flushing.send_replace(true);
+    let tasks = std::mem::take(&mut *self.pending.lock().unwrap());
+    join_all(tasks.into_values().map(|(_, task)| task)).await;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::atomic::AtomicBool;
+
+  use super::*;
+
+  #[tokio::test]
+  async fn flushing_runs_the_pending_actions_now() {
+    let debouncer = Debouncer::new(Duration::from_secs(60));
+    let ran = Arc::new(AtomicBool::new(false));
+    let cancelled = Arc::new(AtomicBool::new(false));
+    for (room_id, flag) in [(1, &ran), (2, &cancelled)] {
+      let flag = flag.clone();
+      debouncer.schedule(room_id, async move {
+        flag.store(true, Ordering::Relaxed);
+      });
+    }
+    assert!(debouncer.cancel(2));
+
+    tokio::time::timeout(Duration::from_secs(1), debouncer.flush())
+      .await
+      .unwrap();
+    assert!(ran.load(Ordering::Relaxed));
+    assert!(!cancelled.load(Ordering::Relaxed));
+  }
+}
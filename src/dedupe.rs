@@ -0,0 +1,94 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// recently processed event ids, the recorder resends an event when its
+/// request times out
+pub struct Dedupe {
+  /// 0 disables
+  pub capacity: usize,
+  pub ttl: Duration,
+  pub seen: Mutex<Seen>,
+}
+
+#[derive(Default)]
+pub struct Seen {
+  ids: HashSet<String>,
+  /// oldest first
+  order: VecDeque<(Instant, String)>,
+}
+
+impl Dedupe {
+  /// whether the id wasn't processed recently, remembering it if so
+  pub fn first_seen(&self, event_id: &str) -> bool {
+    if self.capacity == 0 || event_id.is_empty() {
+      return true;
+    }
+
+    let mut seen = self.seen.lock().unwrap();
+    let now = Instant::now();
+    while let Some((at, _)) = seen.order.front() {
+      if now.duration_since(*at) < self.ttl && seen.order.len() < self.capacity {
+        break;
+      }
+      let (_, id) = seen.order.pop_front().unwrap();
+      seen.ids.remove(&id);
+    }
+
+    if !seen.ids.insert(event_id.to_string()) {
+      return false;
+    }
+    seen.order.push_back((now, event_id.to_string()));
+    true
+  }
+
+  /// for an event that failed, so the recorder's retry of it goes through
+  pub fn forget(&self, event_id: &str) {
+    let mut seen = self.seen.lock().unwrap();
+    if seen.ids.remove(event_id) {
+      seen.order.retain(|(_, it)| it != event_id);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn dedupe() -> Dedupe {
+    Dedupe {
+      capacity: 2,
+      ttl: Duration::from_secs(60),
+      seen: Default::default(),
+    }
+  }
+
+  #[test]
+  fn second_is_a_duplicate() {
+    let dedupe = dedupe();
+    assert!(dedupe.first_seen("a"));
+    assert!(!dedupe.first_seen("a"));
+    assert!(dedupe.first_seen("b"));
+    // without an id nothing is deduplicated
+    assert!(dedupe.first_seen(""));
+    assert!(dedupe.first_seen(""));
+  }
+
+  #[test]
+  fn oldest_is_dropped_past_capacity() {
+    let dedupe = dedupe();
+    assert!(dedupe.first_seen("a"));
+    assert!(dedupe.first_seen("b"));
+    assert!(dedupe.first_seen("c"));
+    assert!(dedupe.first_seen("a"));
+  }
+
+  #[test]
+  fn forgotten_goes_through_again() {
+    let dedupe = dedupe();
+    assert!(dedupe.first_seen("a"));
+    dedupe.forget("a");
+    assert!(dedupe.first_seen("a"));
+    assert!(!dedupe.first_seen("a"));
+  }
+}
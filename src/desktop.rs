@@ -0,0 +1,593 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+#[cfg(feature = "desktop-notifications")]
+use notify_rust::{Notification, Timeout};
+use tracing::debug;
+#[cfg(all(feature = "desktop-notifications", unix, not(target_os = "macos")))]
+use tracing::{error, info};
+
+use crate::images::ImageCache;
+use crate::notifier::{Message, Notifier};
+#[cfg(all(feature = "desktop-notifications", unix, not(target_os = "macos")))]
+use crate::opener;
+
+#[cfg(feature = "desktop-notifications")]
+pub use notify_rust::error::Error;
+#[cfg(all(feature = "desktop-notifications", not(target_os = "windows")))]
+pub use notify_rust::NotificationHandle;
+
+#[cfg(all(feature = "desktop-notifications", target_os = "windows"))]
+mod toast;
+
+#[cfg(target_os = "macos")]
+static SOUND: &str = "Submarine";
+
+#[cfg(all(unix, not(target_os = "macos")))]
+static SOUND: &str = "message-new-instant";
+
+#[cfg(target_os = "windows")]
+static SOUND: &str = "Mail";
+
+/// of the desktop among the notifiers in the config file
+pub static NAME: &str = "desktop";
+
+/// whether this build shows notifications on the desktop at all, without the
+/// `desktop-notifications` feature they go where --no-desktop-notify sends them
+pub static ENABLED: bool = cfg!(feature = "desktop-notifications");
+
+/// stands in for notify-rust's, which has none on windows, where toasts can't
+/// be closed or updated, or in a build without `desktop-notifications`
+#[cfg(not(all(feature = "desktop-notifications", not(target_os = "windows"))))]
+pub struct NotificationHandle;
+
+/// of showing a notification in a build without `desktop-notifications`
+#[cfg(not(feature = "desktop-notifications"))]
+#[derive(Debug)]
+pub struct Error;
+
+/// how desktop notifications are shown
+#[derive(Debug)]
+#[cfg_attr(not(feature = "desktop-notifications"), allow(dead_code))]
+pub struct DesktopOptions {
+  /// sound name, or on linux a path to a sound file, `None` is silent
+  pub sound: Option<String>,
+  /// only used on linux
+  #[cfg_attr(
+    not(all(feature = "desktop-notifications", unix, not(target_os = "macos"))),
+    allow(dead_code)
+  )]
+  pub urgency: Option<Urgency>,
+  pub timeout: NotificationTimeout,
+  /// not shown on macos, which goes by the app of [`Self::app_id`]
+  pub app_name: Option<String>,
+  /// the AUMID toasts are shown as on windows, the bundle identifier of the app
+  /// notifications come from on macos
+  pub app_id: Option<String>,
+  /// a room's notification replaces its last one, only on linux
+  pub group_by_room: bool,
+  /// id of the last notification of each room
+  pub groups: Mutex<HashMap<i64, u32>>,
+  /// times [`show_retrying`] tries again
+  pub retries: u32,
+}
+
+/// the platform default sound unless another one is given or it's turned off
+pub fn sound(sound: Option<String>, no_sound: bool) -> Option<String> {
+  match (no_sound, sound) {
+    (true, _) => None,
+    (false, Some(sound)) => Some(sound),
+    (false, None) => Some(SOUND.to_string()),
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Urgency {
+  Low,
+  Normal,
+  Critical,
+}
+
+impl FromStr for Urgency {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "low" => Ok(Self::Low),
+      "normal" => Ok(Self::Normal),
+      "critical" => Ok(Self::Critical),
+      _ => Err(format!(
+        "unknown urgency `{s}`, expected low, normal or critical"
+      )),
+    }
+  }
+}
+
+/// how long notifications stay on screen, sent as the expire timeout on linux,
+/// where daemons may ignore it, on windows toasts are only short or long, from
+/// 25s on, and macos has no say in it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NotificationTimeout {
+  /// decided by the notification daemon
+  #[default]
+  Default,
+  /// until dismissed
+  Never,
+  Milliseconds(u32),
+}
+
+impl FromStr for NotificationTimeout {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "default" => Ok(Self::Default),
+      "never" => Ok(Self::Never),
+      ms => u32::from_str(ms)
+        .map(Self::Milliseconds)
+        .map_err(|_| format!("invalid timeout `{s}`, expected milliseconds, never or default")),
+    }
+  }
+}
+
+#[cfg(feature = "desktop-notifications")]
+impl From<NotificationTimeout> for Timeout {
+  fn from(value: NotificationTimeout) -> Self {
+    match value {
+      NotificationTimeout::Default => Timeout::Default,
+      NotificationTimeout::Never => Timeout::Never,
+      NotificationTimeout::Milliseconds(ms) => Timeout::Milliseconds(ms),
+    }
+  }
+}
+
+/// what happens to a stream start notification once the stream ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnStreamEnd {
+  Keep,
+  Close,
+  Update,
+}
+
+impl FromStr for OnStreamEnd {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "keep" => Ok(Self::Keep),
+      "close" => Ok(Self::Close),
+      "update" => Ok(Self::Update),
+      _ => Err(format!(
+        "unknown action `{s}`, expected keep, close or update"
+      )),
+    }
+  }
+}
+
+/// shown stream start notifications by room, to close or update them when the
+/// stream ends, only done on linux
+pub struct StartNotifications {
+  pub on_end: OnStreamEnd,
+  /// for rooms that never send an end
+  pub ttl: Duration,
+  pub handles: Mutex<HashMap<i64, (Instant, NotificationHandle)>>,
+}
+
+impl StartNotifications {
+  pub fn insert(&self, room_id: i64, handle: NotificationHandle) {
+    if self.on_end == OnStreamEnd::Keep {
+      return;
+    }
+    let mut handles = self.handles.lock().unwrap();
+    let now = Instant::now();
+    handles.retain(|_, (shown, _)| now.duration_since(*shown) < self.ttl);
+    handles.insert(room_id, (now, handle));
+  }
+
+  /// close the room's start notification, or append `ended` to its body
+  pub fn end(&self, room_id: i64, ended: String) {
+    let Some((_, handle)) = self.handles.lock().unwrap().remove(&room_id) else {
+      return;
+    };
+
+    #[cfg(all(feature = "desktop-notifications", unix, not(target_os = "macos")))]
+    {
+      let on_end = self.on_end;
+      let mut handle = handle;
+      // both are blocking dbus calls
+      tokio::task::spawn_blocking(move || match on_end {
+        OnStreamEnd::Keep => {}
+        OnStreamEnd::Close => {
+          handle.close();
+          info!("{room_id} start notification closed");
+        }
+        OnStreamEnd::Update => {
+          let body = format!("{}\n\n{ended}", handle.body);
+          handle.body(&body);
+          handle.update();
+          info!("{room_id} start notification updated");
+        }
+      });
+    }
+    #[cfg(not(all(feature = "desktop-notifications", unix, not(target_os = "macos"))))]
+    let _ = (handle, ended);
+  }
+}
+
+/// where notifications go when they can't be shown on the desktop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fallback {
+  /// a line on stdout
+  Stdout,
+  /// the line, ringing the terminal bell
+  Bell,
+  None,
+}
+
+impl FromStr for Fallback {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "stdout" => Ok(Self::Stdout),
+      "bell" => Ok(Self::Bell),
+      "none" => Ok(Self::None),
+      _ => Err(format!(
+        "unknown fallback `{s}`, expected stdout, bell or none"
+      )),
+    }
+  }
+}
+
+impl Fallback {
+  /// print the notification as one line, returns whether it went anywhere
+  pub fn print(self, summary: &str, body: &str) -> bool {
+    let bell = match self {
+      Self::Stdout => "",
+      Self::Bell => "\x07",
+      Self::None => return false,
+    };
+    let body = body
+      .lines()
+      .filter(|it| !it.trim().is_empty())
+      .collect::<Vec<_>>()
+      .join(" / ");
+    let time = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+    println!("{bell}[{time}] {summary}: {body}");
+    true
+  }
+}
+
+/// consecutive failures to show notifications, after `threshold` of them the
+/// notification daemon is taken as unavailable until one succeeds
+pub struct DaemonStatus {
+  pub threshold: u32,
+  pub failures: AtomicU32,
+}
+
+impl DaemonStatus {
+  /// returns whether the daemon is unavailable now, and whether it just became so
+  pub fn failed(&self) -> (bool, bool) {
+    let failures = self.failures.fetch_add(1, Ordering::Relaxed) + 1;
+    (failures >= self.threshold, failures == self.threshold)
+  }
+
+  /// returns whether the daemon was unavailable
+  pub fn succeeded(&self) -> bool {
+    self.failures.swap(0, Ordering::Relaxed) >= self.threshold
+  }
+}
+
+/// on macos notifications come from one app for the whole process, so its
+/// bundle identifier is set once before any are shown
+pub fn set_app_id(options: &DesktopOptions) -> Result<(), String> {
+  #[cfg(all(feature = "desktop-notifications", target_os = "macos"))]
+  if let Some(app_id) = &options.app_id {
+    notify_rust::set_application(app_id).map_err(|err| format!("{err:#?}"))?;
+  }
+  // read by show on windows, linux has no use for it
+  #[cfg(not(all(feature = "desktop-notifications", target_os = "macos")))]
+  let _ = &options.app_id;
+  Ok(())
+}
+
+/// shows messages on the desktop, stream starts with the streamer's avatar and
+/// the live cover, keeping those to close or update them when the stream ends
+pub struct DesktopNotifier {
+  pub options: Arc<DesktopOptions>,
+  pub images: Option<Arc<ImageCache>>,
+  pub start_notifications: Arc<StartNotifications>,
+}
+
+#[async_trait]
+impl Notifier for DesktopNotifier {
+  fn name(&self) -> &'static str {
+    NAME
+  }
+
+  async fn send(&self, message: &Message) -> Result<(), String> {
+    let started_room = message
+      .event
+      .as_ref()
+      .filter(|it| it.event_type == "StreamStarted")
+      .map(|it| it.event_data.room_id);
+    let (avatar, cover) = match (&self.images, started_room) {
+      (Some(images), Some(room_id)) => tokio::join!(images.avatar(room_id), images.cover(room_id)),
+      _ => (None, None),
+    };
+    let handle = show_retrying(&self.options, message, avatar.as_deref(), cover.as_deref())
+      .await
+      .map_err(|err| format!("{err:#?}"))?;
+    if let Some(room_id) = started_room {
+      self.start_notifications.insert(room_id, handle);
+    }
+    Ok(())
+  }
+}
+
+/// between the attempts of [`show_retrying`]
+static RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// [`show`], tried again shortly a few times, as the notification daemon may
+/// not be up yet right after login, only the last error is returned
+async fn show_retrying(
+  options: &Arc<DesktopOptions>,
+  message: &Message,
+  icon: Option<&Path>,
+  image: Option<&Path>,
+) -> Result<NotificationHandle, Error> {
+  let mut attempt = 0;
+  loop {
+    match show(options, message, icon, image).await {
+      Err(err) if attempt < options.retries => {
+        debug!("failed to show notification, trying again\n{err:#?}");
+        tokio::time::sleep(RETRY_DELAY).await;
+        attempt += 1;
+      }
+      result => return result,
+    }
+  }
+}
+
+/// [`show_blocking`] on a blocking thread, it waits on the notification daemon
+async fn show(
+  options: &Arc<DesktopOptions>,
+  message: &Message,
+  icon: Option<&Path>,
+  image: Option<&Path>,
+) -> Result<NotificationHandle, Error> {
+  #[cfg(all(feature = "desktop-notifications", unix, not(target_os = "macos")))]
+  if message.url.is_some() {
+    listen_for_clicks().await;
+  }
+  let options = options.clone();
+  let message = message.clone();
+  let icon = icon.map(Path::to_path_buf);
+  let image = image.map(Path::to_path_buf);
+  tokio::task::spawn_blocking(move || {
+    show_blocking(&options, &message, icon.as_deref(), image.as_deref())
+  })
+  .await
+  .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))
+}
+
+/// whether notifications can carry an image besides the icon
+pub static SUPPORTS_IMAGES: bool = cfg!(all(
+  feature = "desktop-notifications",
+  not(target_os = "macos")
+));
+
+/// never shows anything, `ENABLED` is false
+#[cfg(not(feature = "desktop-notifications"))]
+fn show_blocking(
+  _options: &DesktopOptions,
+  _message: &Message,
+  _icon: Option<&Path>,
+  _image: Option<&Path>,
+) -> Result<NotificationHandle, Error> {
+  Err(Error)
+}
+
+/// show a notification, activating it opens the url on linux and windows, where
+/// toasts also get "Open room" and "Dismiss" buttons, macos doesn't report
+/// activation back through notify-rust, `icon` and `image` aren't shown on
+/// macos, on windows `image` takes the place of `icon`
+#[cfg(feature = "desktop-notifications")]
+fn show_blocking(
+  options: &DesktopOptions,
+  message: &Message,
+  icon: Option<&Path>,
+  image: Option<&Path>,
+) -> Result<NotificationHandle, Error> {
+  let mut notification = Notification::new();
+  notification
+    .summary(&message.summary)
+    .body(&message.body)
+    .timeout(options.timeout);
+  if let Some(app_name) = &options.app_name {
+    notification.appname(app_name);
+  }
+  #[cfg(target_os = "windows")]
+  if let Some(app_id) = &options.app_id {
+    notification.app_id(app_id);
+  }
+
+  #[cfg(all(unix, not(target_os = "macos")))]
+  {
+    if let Some(icon) = icon.and_then(Path::to_str) {
+      notification.icon(icon);
+    }
+    if let Some(image) = image.and_then(Path::to_str) {
+      notification.image_path(image);
+    }
+  }
+  // toasts from notify-rust have a single image
+  #[cfg(target_os = "windows")]
+  if let Some(image) = image.or(icon).and_then(Path::to_str) {
+    notification.image_path(image);
+  }
+  #[cfg(target_os = "macos")]
+  let _ = (icon, image);
+
+  #[cfg(all(unix, not(target_os = "macos")))]
+  if let Some(urgency) = message
+    .urgent
+    .then_some(Urgency::Critical)
+    .or(options.urgency)
+  {
+    notification.urgency(match urgency {
+      Urgency::Low => notify_rust::Urgency::Low,
+      Urgency::Normal => notify_rust::Urgency::Normal,
+      Urgency::Critical => notify_rust::Urgency::Critical,
+    });
+  }
+
+  match options.sound.as_deref() {
+    // daemons that support it play the file from the `sound-file` hint
+    #[cfg(all(unix, not(target_os = "macos")))]
+    Some(path) if path.contains('/') => {
+      notification.hint(notify_rust::Hint::SoundFile(path.to_string()));
+    }
+    Some(name) => {
+      notification.sound_name(name);
+    }
+    None => {}
+  }
+
+  #[cfg(all(unix, not(target_os = "macos")))]
+  if message.url.is_some() {
+    notification.action("default", "Open room");
+  }
+
+  // the id replaces the notification on any daemon, the tag is for dunst, which
+  // also replaces ones it doesn't know the id of anymore
+  #[cfg(all(unix, not(target_os = "macos")))]
+  let group = message.room_id.filter(|_| options.group_by_room);
+  #[cfg(all(unix, not(target_os = "macos")))]
+  if let Some(room_id) = group {
+    if let Some(id) = options.groups.lock().unwrap().get(&room_id) {
+      notification.id(*id);
+    }
+    notification.hint(notify_rust::Hint::Custom(
+      "x-dunst-stack-tag".to_string(),
+      room_id.to_string(),
+    ));
+  }
+  #[cfg(not(all(unix, not(target_os = "macos"))))]
+  let _ = (options.group_by_room, &options.groups);
+
+  // the buttons need a toast of our own
+  #[cfg(target_os = "windows")]
+  if let Some(url) = &message.url {
+    return toast::show(options, message, url, image.or(icon)).map(|()| NotificationHandle);
+  }
+
+  // notify-rust's toasts have no handle
+  #[cfg(target_os = "windows")]
+  let handle = notification.show().map(|()| NotificationHandle)?;
+  #[cfg(not(target_os = "windows"))]
+  let handle = notification.show()?;
+
+  #[cfg(all(unix, not(target_os = "macos")))]
+  if let Some(room_id) = group {
+    options.groups.lock().unwrap().insert(room_id, handle.id());
+  }
+
+  #[cfg(all(unix, not(target_os = "macos")))]
+  if let Some(url) = message.url.clone() {
+    let id = handle.id();
+    let mut urls = CLICK_URLS.lock().unwrap();
+    urls.retain(|(it, _)| *it != id);
+    if urls.len() >= MAX_CLICK_URLS {
+      urls.remove(0);
+    }
+    urls.push((id, url));
+  }
+
+  Ok(handle)
+}
+
+/// the url to open for each notification shown with one, by id, oldest first,
+/// until it's closed
+#[cfg(all(feature = "desktop-notifications", unix, not(target_os = "macos")))]
+static CLICK_URLS: Mutex<Vec<(u32, String)>> = Mutex::new(Vec::new());
+
+/// daemons that keep notifications around don't say when they're gone
+#[cfg(all(feature = "desktop-notifications", unix, not(target_os = "macos")))]
+static MAX_CLICK_URLS: usize = 100;
+
+/// set once [`listen_for_clicks`] is, whether it could or not
+#[cfg(all(feature = "desktop-notifications", unix, not(target_os = "macos")))]
+static LISTENING: tokio::sync::OnceCell<()> = tokio::sync::OnceCell::const_new();
+
+/// from the first notification with a url on, before it's shown so its click
+/// can't come first, opens the url of a notification when it's activated, with
+/// no thread of its own waiting on each one
+#[cfg(all(feature = "desktop-notifications", unix, not(target_os = "macos")))]
+async fn listen_for_clicks() {
+  LISTENING
+    .get_or_init(|| async {
+      match subscribe().await {
+        Ok((invoked, closed)) => {
+          tokio::spawn(open_on_click(invoked, closed));
+        }
+        Err(err) => error!("failed to listen for notification clicks\n{err}"),
+      }
+    })
+    .await;
+}
+
+#[cfg(all(feature = "desktop-notifications", unix, not(target_os = "macos")))]
+async fn subscribe() -> zbus::Result<(zbus::SignalStream<'static>, zbus::SignalStream<'static>)> {
+  let conn = zbus::Connection::session().await?;
+  let daemon = zbus::Proxy::new(
+    &conn,
+    "org.freedesktop.Notifications",
+    "/org/freedesktop/Notifications",
+    "org.freedesktop.Notifications",
+  )
+  .await?;
+  let invoked = daemon.receive_signal("ActionInvoked").await?;
+  let closed = daemon.receive_signal("NotificationClosed").await?;
+  Ok((invoked, closed))
+}
+
+#[cfg(all(feature = "desktop-notifications", unix, not(target_os = "macos")))]
+async fn open_on_click(
+  mut invoked: zbus::SignalStream<'static>,
+  mut closed: zbus::SignalStream<'static>,
+) {
+  use futures_util::StreamExt;
+
+  loop {
+    tokio::select! {
+      Some(signal) = invoked.next() => {
+        let Ok((id, action)) = signal.body::<(u32, String)>() else {
+          continue;
+        };
+        let url = CLICK_URLS
+          .lock()
+          .unwrap()
+          .iter()
+          .find(|(it, _)| *it == id)
+          .map(|(_, url)| url.clone());
+        if let (Some(url), "default") = (url, action.as_str()) {
+          if let Err(err) = opener::open(&url, None) {
+            error!("failed to open {url}\n{err:#?}");
+          }
+        }
+      }
+      Some(signal) = closed.next() => {
+        if let Ok((id, _reason)) = signal.body::<(u32, u32)>() {
+          CLICK_URLS.lock().unwrap().retain(|(it, _)| *it != id);
+        }
+      }
+      else => return,
+    }
+  }
+}
@@ -0,0 +1,93 @@
+use std::path::Path;
+
+use notify_rust::error::{Error, ErrorKind};
+use windows::core::HSTRING;
+use windows::Data::Xml::Dom::XmlDocument;
+use windows::UI::Notifications::{ToastNotification, ToastNotificationManager};
+
+use crate::desktop::{DesktopOptions, NotificationTimeout};
+use crate::notifier::Message;
+
+/// as notify-rust shows toasts when no `--app-id` is given
+static POWERSHELL_APP_ID: &str =
+  "{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\\WindowsPowerShell\\v1.0\\powershell.exe";
+
+/// a toast with "Open room" and "Dismiss" buttons, which notify-rust's toasts
+/// don't have
+///
+/// clicking the toast or "Open room" opens `url` by protocol activation, so
+/// windows hands it to the default browser and nothing has to wait for the
+/// click, "Dismiss" is handled by windows itself. toasts only show, buttons
+/// included, for an AUMID of a start menu shortcut, like powershell's default
+/// one, an `--app-id` without a shortcut shows nothing
+pub fn show(
+  options: &DesktopOptions,
+  message: &Message,
+  url: &str,
+  image: Option<&Path>,
+) -> notify_rust::error::Result<()> {
+  let app_id = options.app_id.as_deref().unwrap_or(POWERSHELL_APP_ID);
+  let xml = xml(options, message, url, image);
+  shown(app_id, &xml).map_err(|err| Error::from(ErrorKind::Msg(format!("{err:?}"))))
+}
+
+fn shown(app_id: &str, xml: &str) -> windows::core::Result<()> {
+  let document = XmlDocument::new()?;
+  document.LoadXml(&HSTRING::from(xml))?;
+  let toast = ToastNotification::CreateToastNotification(&document)?;
+  ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(app_id))?.Show(&toast)
+}
+
+fn xml(options: &DesktopOptions, message: &Message, url: &str, image: Option<&Path>) -> String {
+  let url = escape(url);
+  // like notify-rust, toasts are only short or long
+  let duration = match options.timeout {
+    NotificationTimeout::Never => "long",
+    NotificationTimeout::Milliseconds(ms) if ms >= 25000 => "long",
+    _ => "short",
+  };
+  let image = image
+    .and_then(Path::to_str)
+    .map(|it| {
+      format!(
+        r#"<image placement="appLogoOverride" src="file:///{}"/>"#,
+        escape(it)
+      )
+    })
+    .unwrap_or_default();
+  // names like Mail, IM or Reminder, as with notify-rust
+  let audio = match &options.sound {
+    Some(name) => format!(
+      r#"<audio src="ms-winsoundevent:Notification.{}"/>"#,
+      escape(name)
+    ),
+    None => r#"<audio silent="true"/>"#.to_string(),
+  };
+  format!(
+    r#"<toast duration="{duration}" activationType="protocol" launch="{url}">
+  <visual>
+    <binding template="ToastGeneric">
+      <text>{}</text>
+      <text>{}</text>
+      {image}
+    </binding>
+  </visual>
+  {audio}
+  <actions>
+    <action content="Open room" activationType="protocol" arguments="{url}"/>
+    <action content="Dismiss" activationType="system" arguments="dismiss"/>
+  </actions>
+</toast>"#,
+    escape(&message.summary),
+    escape(&message.body),
+  )
+}
+
+fn escape(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+    .replace('\'', "&apos;")
+}
@@ -0,0 +1,35 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::sync::Notify;
+
+use crate::Event;
+
+/// stream starts within `window` of the first one, sent together once it passes
+pub struct StartDigest {
+  /// 0 disables
+  pub window: Duration,
+  /// fewer starts than this are notified one by one
+  pub min: usize,
+  pub pending: Mutex<Vec<Event>>,
+  /// the first start of a window wakes the sender up
+  pub wake: Notify,
+}
+
+impl StartDigest {
+  pub fn enabled(&self) -> bool {
+    !self.window.is_zero()
+  }
+
+  pub fn add(&self, event: Event) {
+    let mut pending = self.pending.lock().unwrap();
+    if pending.is_empty() {
+      self.wake.notify_one();
+    }
+    pending.push(event);
+  }
+
+  pub fn take(&self) -> Vec<Event> {
+    std::mem::take(&mut *self.pending.lock().unwrap())
+  }
+}
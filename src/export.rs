@@ -0,0 +1,124 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use chrono::{DateTime, FixedOffset, Local, SecondsFormat};
+
+use crate::stats::Range;
+use crate::Event;
+
+/// webhooks kept for /events.csv, the oldest are dropped past this
+static MAX_ENTRIES: usize = 10000;
+
+/// so excel takes the file as UTF-8, or chinese names come out garbled
+static BOM: &str = "\u{feff}";
+
+static HEADER: &str =
+  "received_at,event_type,room_id,short_id,name,title,area_parent,area_child,notified,decision";
+
+/// the webhooks received, with what was done with them, for `GET /events.csv`
+#[derive(Default)]
+pub struct EventLog {
+  entries: Mutex<VecDeque<Logged>>,
+}
+
+struct Logged {
+  received_at: DateTime<FixedOffset>,
+  event: Event,
+  decision: &'static str,
+}
+
+impl EventLog {
+  pub fn add(&self, event: &Event, decision: &'static str) {
+    let mut entries = self.entries.lock().unwrap();
+    if entries.len() >= MAX_ENTRIES {
+      entries.pop_front();
+    }
+    entries.push_back(Logged {
+      received_at: Local::now().fixed_offset(),
+      event: event.clone(),
+      decision,
+    });
+  }
+
+  /// the last `count` received, newest first
+  pub fn recent(&self, count: usize) -> Vec<(DateTime<FixedOffset>, Event)> {
+    let entries = self.entries.lock().unwrap();
+    entries
+      .iter()
+      .rev()
+      .take(count)
+      .map(|it| (it.received_at, it.event.clone()))
+      .collect()
+  }
+
+  /// those received within `range`, oldest first
+  pub fn csv(&self, range: &Range, bom: bool) -> String {
+    let mut csv = String::new();
+    if bom {
+      csv += BOM;
+    }
+    csv += HEADER;
+    csv += "\r\n";
+    for logged in self.entries.lock().unwrap().iter() {
+      if !range.contains(logged.received_at) {
+        continue;
+      }
+      let data = &logged.event.event_data;
+      // a notification in the background is taken as shown
+      let notified = matches!(logged.decision, "notified" | "acknowledged");
+      let fields = [
+        logged
+          .received_at
+          .to_rfc3339_opts(SecondsFormat::Secs, false),
+        logged.event.event_type.clone(),
+        data.room_id.to_string(),
+        data.short_id.to_string(),
+        data.name.clone(),
+        data.title.clone(),
+        data.area_name_parent.clone(),
+        data.area_name_child.clone(),
+        notified.to_string(),
+        logged.decision.to_string(),
+      ];
+      for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+          csv.push(',');
+        }
+        csv += &quoted(field);
+      }
+      csv += "\r\n";
+    }
+    csv
+  }
+}
+
+/// as RFC 4180 has it, in quotes with quotes doubled when it holds a comma, a
+/// quote or a line break, and behind a `'` when it starts like a formula, so a
+/// streamer's title doesn't run in the spreadsheet it's opened in
+fn quoted(field: &str) -> Cow<'_, str> {
+  let field: Cow<'_, str> = match field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+    true => format!("'{field}").into(),
+    false => field.into(),
+  };
+  if field.contains([',', '"', '\r', '\n']) {
+    format!("\"{}\"", field.replace('"', "\"\"")).into()
+  } else {
+    field
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn formulas_are_escaped() {
+    assert_eq!(quoted("=HYPERLINK(\"x\")"), "\"'=HYPERLINK(\"\"x\"\")\"");
+    assert_eq!(quoted("+1"), "'+1");
+    assert_eq!(quoted("-1"), "'-1");
+    assert_eq!(quoted("@SUM(A1)"), "'@SUM(A1)");
+    assert_eq!(quoted("a=b"), "a=b");
+    assert_eq!(quoted("a,b"), "\"a,b\"");
+  }
+}
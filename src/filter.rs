@@ -0,0 +1,192 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use serde::{de, Deserialize, Deserializer};
+
+/// room ids and ranges of them, like `1,5,1000-1050`
+#[derive(Clone, PartialEq, Eq)]
+pub struct RoomFilter(Vec<RangeInclusive<u32>>);
+
+impl FromStr for RoomFilter {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let parse_id =
+      |it: &str| u32::from_str(it.trim()).map_err(|_| format!("invalid room id `{}`", it.trim()));
+
+    let mut ranges = vec![];
+    for item in s.split(',').filter(|it| !it.trim().is_empty()) {
+      let range = match item.split_once('-') {
+        Some((start, end)) => {
+          let (start, end) = (parse_id(start)?, parse_id(end)?);
+          if start > end {
+            return Err(format!("inverted room id range `{}`", item.trim()));
+          }
+          start..=end
+        }
+        None => {
+          let id = parse_id(item)?;
+          id..=id
+        }
+      };
+      ranges.push(range);
+    }
+    Ok(Self(ranges))
+  }
+}
+
+/// the same format as on the command line, like `rooms = "1,5,1000-1050"`
+impl<'de> Deserialize<'de> for RoomFilter {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let src = String::deserialize(deserializer)?;
+    Self::from_str(&src).map_err(de::Error::custom)
+  }
+}
+
+impl RoomFilter {
+  pub fn contains(&self, room_id: i64) -> bool {
+    u32::try_from(room_id).is_ok_and(|id| self.0.iter().any(|it| it.contains(&id)))
+  }
+
+  /// every room id of the filter, ranges included
+  pub fn ids(&self) -> impl Iterator<Item = i64> + '_ {
+    self.0.iter().cloned().flatten().map(i64::from)
+  }
+}
+
+impl fmt::Debug for RoomFilter {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let items = self
+      .0
+      .iter()
+      .map(|it| match it.start() == it.end() {
+        true => it.start().to_string(),
+        false => format!("{}-{}", it.start(), it.end()),
+      })
+      .collect::<Vec<_>>();
+    write!(f, "{}", items.join(", "))
+  }
+}
+
+/// parent area names, like `网游,单机游戏`, compared case insensitively
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AreaFilter(Vec<String>);
+
+impl FromStr for AreaFilter {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let areas = s
+      .split(',')
+      .map(|it| it.trim().to_lowercase())
+      .filter(|it| !it.is_empty())
+      .collect::<Vec<_>>();
+    match areas.is_empty() {
+      true => Err("no area names given".to_string()),
+      false => Ok(Self(areas)),
+    }
+  }
+}
+
+impl AreaFilter {
+  pub fn contains(&self, area: &str) -> bool {
+    let area = area.trim().to_lowercase();
+    self.0.contains(&area)
+  }
+}
+
+/// how the room and area filter combine when both are given
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Combine {
+  /// both have to match
+  And,
+  /// either one
+  Or,
+}
+
+impl FromStr for Combine {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "and" => Ok(Self::And),
+      "or" => Ok(Self::Or),
+      _ => Err(format!("unknown combination `{s}`, expected and or or")),
+    }
+  }
+}
+
+impl Combine {
+  /// whether an event passes, given which filters are set and matched
+  pub fn passes(self, room: Option<bool>, area: Option<bool>) -> bool {
+    match (room, area) {
+      (Some(room), Some(area)) => match self {
+        Self::And => room && area,
+        Self::Or => room || area,
+      },
+      (Some(it), None) | (None, Some(it)) => it,
+      (None, None) => true,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn single_ids_and_ranges() {
+    let filter = RoomFilter::from_str(" 1, 5,1000-1050 ,").unwrap();
+    assert!(filter.contains(1));
+    assert!(filter.contains(5));
+    assert!(filter.contains(1000));
+    assert!(filter.contains(1025));
+    assert!(filter.contains(1050));
+    assert!(!filter.contains(2));
+    assert!(!filter.contains(1051));
+    assert!(!filter.contains(-1));
+    assert_eq!(format!("{filter:?}"), "1, 5, 1000-1050");
+    assert_eq!(
+      RoomFilter::from_str("7-9")
+        .unwrap()
+        .ids()
+        .collect::<Vec<_>>(),
+      [7, 8, 9]
+    );
+  }
+
+  #[test]
+  fn invalid_filters() {
+    assert_eq!(
+      RoomFilter::from_str("1050-1000"),
+      Err("inverted room id range `1050-1000`".to_string())
+    );
+    assert_eq!(
+      RoomFilter::from_str("1,abc"),
+      Err("invalid room id `abc`".to_string())
+    );
+    assert!(RoomFilter::from_str("1-").is_err());
+  }
+
+  #[test]
+  fn areas_compare_case_insensitively() {
+    let filter = AreaFilter::from_str("网游, Single Player ,").unwrap();
+    assert!(filter.contains("网游"));
+    assert!(filter.contains(" single player"));
+    assert!(!filter.contains("单机游戏"));
+    assert!(AreaFilter::from_str(" , ").is_err());
+  }
+
+  #[test]
+  fn filters_combine() {
+    // only an area filter
+    assert!(Combine::And.passes(None, Some(true)));
+    assert!(!Combine::And.passes(None, Some(false)));
+    assert!(!Combine::Or.passes(None, Some(false)));
+
+    assert!(!Combine::And.passes(Some(true), Some(false)));
+    assert!(Combine::Or.passes(Some(true), Some(false)));
+    assert!(Combine::And.passes(None, None));
+  }
+}
@@ -0,0 +1,18 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// a per room flag of the event data as of the room's last event, for
+/// --notify-danmaku and --notify-recording-start
+#[derive(Default)]
+pub struct RoomFlag {
+  pub last: Mutex<HashMap<i64, bool>>,
+}
+
+impl RoomFlag {
+  /// whether the room's flag flipped since its last event, the first event of
+  /// a room only says how it starts out
+  pub fn flipped(&self, room_id: i64, value: bool) -> bool {
+    let previous = self.last.lock().unwrap().insert(room_id, value);
+    previous.is_some_and(|it| it != value)
+  }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Deserialize;
+use tracing::{error, info};
+
+use crate::notifier;
+use crate::template::{Engine, Template};
+use crate::Event;
+
+/// unless a target has a `timeout_secs` of its own
+static TIMEOUT: Duration = Duration::from_secs(10);
+
+/// settings of a `--forward-url`, from its `[forward."<url>"]` table
+#[derive(Deserialize, Default, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ForwardConfig {
+  /// the json to post, with the placeholders of the notification templates,
+  /// instead of the event as received, its own braces doubled with the simple
+  /// engine, like `{{"text": "{name} is live"}}`
+  pub template: Option<String>,
+  /// sent with every request, like `Authorization`
+  #[serde(default)]
+  pub headers: HashMap<String, String>,
+  pub timeout_secs: Option<u64>,
+}
+
+/// somewhere accepted events are posted to
+pub struct ForwardTarget {
+  client: reqwest::Client,
+  url: String,
+  /// logged instead of the url, which may carry a secret
+  host: String,
+  template: Option<Template>,
+  headers: HeaderMap,
+  timeout: Duration,
+}
+
+/// a target for each url, set up by its table in `config` if there's one
+pub fn targets(
+  urls: &[String],
+  engine: Engine,
+  config: &HashMap<String, ForwardConfig>,
+) -> Result<Vec<ForwardTarget>, String> {
+  if let Some(url) = config.keys().find(|it| !urls.contains(it)) {
+    return Err(format!("forward target `{url}` isn't a --forward-url"));
+  }
+
+  let mut targets = vec![];
+  for url in urls {
+    let config = config.get(url);
+    let host = reqwest::Url::parse(url)
+      .map_err(|err| format!("invalid --forward-url `{url}`: {err}"))?
+      .host_str()
+      .unwrap_or_default()
+      .to_string();
+    let template = config
+      .and_then(|it| it.template.as_deref())
+      .map(|it| Template::parse_with(engine, it, None))
+      .transpose()
+      .map_err(|err| format!("invalid template for {url}: {err}"))?;
+    let mut headers = HeaderMap::new();
+    for (name, value) in config.iter().flat_map(|it| &it.headers) {
+      let name = HeaderName::from_str(name).map_err(|_| format!("invalid header `{name}`"))?;
+      let value =
+        HeaderValue::from_str(value).map_err(|_| format!("invalid value of header `{name}`"))?;
+      headers.insert(name, value);
+    }
+    targets.push(ForwardTarget {
+      client: reqwest::Client::new(),
+      url: url.clone(),
+      host,
+      template,
+      headers,
+      timeout: config
+        .and_then(|it| it.timeout_secs)
+        .map_or(TIMEOUT, Duration::from_secs),
+    });
+  }
+  Ok(targets)
+}
+
+impl ForwardTarget {
+  /// retried like the notifiers, failures are only logged
+  pub async fn send(&self, event: &Event) {
+    let body = match &self.template {
+      Some(template) => {
+        let rendered = template.render_json(event);
+        match serde_json::from_str::<serde_json::Value>(&rendered) {
+          Ok(it) => it,
+          Err(err) => {
+            error!(
+              "forward template for {} isn't valid json\n{err}\n{rendered}",
+              self.host
+            );
+            return;
+          }
+        }
+      }
+      None => serde_json::to_value(event).unwrap(),
+    };
+    let req = self
+      .client
+      .post(&self.url)
+      .timeout(self.timeout)
+      .headers(self.headers.clone())
+      .json(&body);
+    match notifier::send_retrying(req).await {
+      Ok(_) => info!("{} forwarded to {}", event.event_id, self.host),
+      Err(err) => error!(
+        "failed to forward {} to {}\n{err}",
+        event.event_id, self.host
+      ),
+    }
+  }
+}
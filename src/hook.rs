@@ -0,0 +1,178 @@
+use std::process::Stdio;
+use std::str::FromStr;
+use std::time::Duration;
+
+use futures_util::future::join_all;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::Semaphore;
+use tracing::{error, info, warn};
+
+use crate::Event;
+
+/// an `--on-event-type`, like `StreamStarted=notify-send started`
+#[derive(Debug, Clone)]
+pub struct TypedCommand {
+  pub event_type: String,
+  pub command: String,
+}
+
+impl FromStr for TypedCommand {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.split_once('=') {
+      Some((event_type, command)) if !event_type.is_empty() => Ok(Self {
+        event_type: event_type.to_string(),
+        command: command.to_string(),
+      }),
+      _ => Err(format!(
+        "invalid event command `{s}`, expected <event type>=<command>"
+      )),
+    }
+  }
+}
+
+/// the commands run for events, at most as many at once as there are slots,
+/// the rest wait for one
+pub struct EventCommands {
+  /// for every event
+  all: Option<EventCommand>,
+  by_type: Vec<(String, EventCommand)>,
+  slots: Semaphore,
+}
+
+impl EventCommands {
+  pub fn new(
+    all: Option<EventCommand>,
+    by_type: Vec<(String, EventCommand)>,
+    concurrency: usize,
+  ) -> Self {
+    Self {
+      all,
+      by_type,
+      slots: Semaphore::new(concurrency),
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.all.is_none() && self.by_type.is_empty()
+  }
+
+  /// every command for the event's type, to completion
+  pub async fn run(&self, event: &Event) {
+    let commands = self.all.iter().chain(
+      self
+        .by_type
+        .iter()
+        .filter(|(event_type, _)| *event_type == event.event_type)
+        .map(|(_, command)| command),
+    );
+    join_all(commands.map(|command| async move {
+      // only closed when dropped
+      let _slot = self.slots.acquire().await.unwrap();
+      command.run(event).await;
+    }))
+    .await;
+  }
+}
+
+/// split like a shell would, quotes and backslashes included, without running
+/// one
+pub fn split(command: &str) -> Result<Vec<String>, String> {
+  shlex::split(command).ok_or_else(|| format!("unbalanced quotes in `{command}`"))
+}
+
+/// a program run for events, with the event as json on stdin and its key
+/// fields as `BILI_*` environment variables, the arguments go to the program as
+/// they are, no shell sees the titles and names of the environment
+pub struct EventCommand {
+  program: String,
+  args: Vec<String>,
+  /// killed after it
+  timeout: Duration,
+}
+
+impl EventCommand {
+  pub fn parse(command: &str, timeout: Duration) -> Result<Self, String> {
+    let mut args = split(command)?.into_iter();
+    let program = args.next().ok_or("empty command")?;
+    Ok(Self {
+      program,
+      args: args.collect(),
+      timeout,
+    })
+  }
+
+  /// run the command to completion, its result is only logged
+  pub async fn run(&self, event: &Event) {
+    let child = Command::new(&self.program)
+      .args(&self.args)
+      .envs(env(event))
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .kill_on_drop(true)
+      .spawn();
+    let mut child = match child {
+      Ok(it) => it,
+      Err(err) => {
+        error!("failed to run {}\n{err:#?}", self.program);
+        return;
+      }
+    };
+
+    let json = serde_json::to_vec(event).unwrap_or_default();
+    let run = async {
+      if let Some(mut stdin) = child.stdin.take() {
+        // the command may not care about stdin and exit before reading it
+        let _ = stdin.write_all(&json).await;
+      }
+      child.wait_with_output().await
+    };
+    let output = match tokio::time::timeout(self.timeout, run).await {
+      Ok(Ok(it)) => it,
+      Ok(Err(err)) => {
+        error!("failed to wait for {}\n{err:#?}", self.program);
+        return;
+      }
+      Err(_) => {
+        warn!(
+          "{} timed out after {:?}, killed",
+          self.program, self.timeout
+        );
+        return;
+      }
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    match output.status.success() {
+      true => info!("{} exited\n{}", self.program, stdout.trim_end()),
+      false => warn!(
+        "{} exited with {}\n{}{}",
+        self.program,
+        output.status,
+        stdout,
+        stderr.trim_end()
+      ),
+    }
+  }
+}
+
+fn env(event: &Event) -> Vec<(&'static str, String)> {
+  let data = &event.event_data;
+  vec![
+    ("BILI_EVENT_TYPE", event.event_type.clone()),
+    ("BILI_EVENT_ID", event.event_id.clone()),
+    ("BILI_EVENT_TIMESTAMP", event.event_timestamp.clone()),
+    ("BILI_ROOM_ID", data.room_id.to_string()),
+    ("BILI_SHORT_ID", data.short_id.to_string()),
+    ("BILI_NAME", data.name.clone()),
+    ("BILI_TITLE", data.title.clone()),
+    ("BILI_AREA_PARENT", data.area_name_parent.clone()),
+    ("BILI_AREA_CHILD", data.area_name_child.clone()),
+    ("BILI_RECORDING", data.recording.to_string()),
+    ("BILI_STREAMING", data.streaming.to_string()),
+  ]
+}
@@ -0,0 +1,313 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, FixedOffset, Local};
+use tracing::warn;
+
+/// language of the built in notification texts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+  En,
+  Zh,
+}
+
+impl FromStr for Lang {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    // locale names like zh_CN.UTF-8 or zh-TW count as their language
+    let code = s.split(['_', '-', '.']).next().unwrap_or_default();
+    match code.to_ascii_lowercase().as_str() {
+      "en" => Ok(Self::En),
+      "zh" => Ok(Self::Zh),
+      _ => Err(format!("unknown language `{s}`, expected zh or en")),
+    }
+  }
+}
+
+/// the built in body of events about the room as a whole, the parts that
+/// are empty or zero left out
+static EN_ROOM_TITLE: &str =
+  "{name} (room {room_id}{?, short {short_id}}){?, {area_parent}{? · {area_child}}}{?\n\n{title}}";
+static ZH_ROOM_TITLE: &str =
+  "{name} (直播间 {room_id}{?, 短号 {short_id}}){?, {area_parent}{? · {area_child}}}{?\n\n{title}}";
+
+impl Lang {
+  /// the language named by `--lang`, english with a warning when it's unknown
+  pub fn from_arg(lang: &str) -> Self {
+    Self::from_str(lang).unwrap_or_else(|err| {
+      warn!("{err}, falling back to en");
+      Self::En
+    })
+  }
+
+  /// from the locale environment variables, english when not set
+  pub fn detect() -> Self {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+      .iter()
+      .filter_map(|it| std::env::var(it).ok())
+      .find(|it| !it.is_empty())
+      .and_then(|it| Self::from_str(&it).ok())
+      .unwrap_or(Self::En)
+  }
+
+  /// built in (summary, body) templates for an event type
+  pub fn templates(self, event_type: &str) -> (&'static str, &'static str) {
+    match (self, event_type) {
+      (Self::En, "StreamStarted") => (
+        "{name} is live!",
+        "{name} is streaming: {title}{? ({area_parent}{? · {area_child}})}{?\nroom {room_id}{? (short {short_id})}}",
+      ),
+      (Self::En, "StreamEnded") => (
+        "{name} went offline",
+        "{name} stopped streaming in room {room_id}{? (short {short_id})}.{?\n{area_parent}{? · {area_child}}}{?\n\n{title}}",
+      ),
+      (Self::En, "SessionStarted") => ("Recording started", EN_ROOM_TITLE),
+      (Self::En, "SessionEnded") => ("Recording stopped", EN_ROOM_TITLE),
+      (Self::En, "FileOpening") => (
+        "New file",
+        "{name} (room {room_id}{?, short {short_id}}){?, {area_parent}{? · {area_child}}}{?\n\n{relative_path}}",
+      ),
+      (Self::En, "FileClosed") => (
+        "Recording saved ({file_size})",
+        "{name} (room {room_id}{?, short {short_id}}), {duration}{?\n\n{relative_path}}",
+      ),
+      (Self::En, _) => ("{name}", EN_ROOM_TITLE),
+
+      (Self::Zh, "StreamStarted") => (
+        "{name} 开播了!",
+        "{name} 开播了: {title}{? ({area_parent}{? · {area_child}})}{?\n直播间 {room_id}{? (短号 {short_id})}}",
+      ),
+      (Self::Zh, "StreamEnded") => (
+        "{name} 下播了",
+        "直播间 {room_id}{? (短号 {short_id})} 的直播已结束{?\n{area_parent}{? · {area_child}}}{?\n\n{title}}",
+      ),
+      (Self::Zh, "SessionStarted") => ("开始录制", ZH_ROOM_TITLE),
+      (Self::Zh, "SessionEnded") => ("录制结束", ZH_ROOM_TITLE),
+      (Self::Zh, "FileOpening") => (
+        "新建文件",
+        "{name} (直播间 {room_id}{?, 短号 {short_id}}){?, {area_parent}{? · {area_child}}}{?\n\n{relative_path}}",
+      ),
+      (Self::Zh, "FileClosed") => (
+        "录制已保存 ({file_size})",
+        "{name} (直播间 {room_id}{?, 短号 {short_id}}), {duration}{?\n\n{relative_path}}",
+      ),
+      (Self::Zh, _) => ("{name}", ZH_ROOM_TITLE),
+    }
+  }
+
+  /// built in `--tts` phrase template for an event type
+  pub fn spoken(self, event_type: &str) -> &'static str {
+    match (self, event_type) {
+      (Self::En, "StreamStarted") => "{name} started streaming",
+      (Self::Zh, "StreamStarted") => "{name} 开播了",
+      _ => self.templates(event_type).0,
+    }
+  }
+
+  /// appended to a stream start notification when the stream ends
+  pub fn ended_at(self, time: &str) -> String {
+    match self {
+      Self::En => format!("ended at {time}"),
+      Self::Zh => format!("已于 {time} 下播"),
+    }
+  }
+
+  /// (summary, body) of the notification sent once --max-notifications lets
+  /// notifications through again
+  pub fn rate_limited(self, suppressed: u64) -> (String, String) {
+    match self {
+      Self::En => (
+        "Rate limit hit".to_string(),
+        format!("{suppressed} notifications suppressed"),
+      ),
+      Self::Zh => (
+        "通知过于频繁".to_string(),
+        format!("已屏蔽 {suppressed} 条通知"),
+      ),
+    }
+  }
+
+  /// added to the body of a StreamEnded whose start was seen, like
+  /// `streamed for 2h 13m`
+  pub fn streamed_for(self, secs: u64) -> String {
+    let (hours, minutes) = (secs / 3600, secs / 60 % 60);
+    match (self, hours) {
+      (Self::En, 0) => format!("streamed for {minutes}m"),
+      (Self::En, _) => format!("streamed for {hours}h {minutes}m"),
+      (Self::Zh, 0) => format!("直播了 {minutes} 分钟"),
+      (Self::Zh, _) => format!("直播了 {hours} 小时 {minutes} 分钟"),
+    }
+  }
+
+  /// (summary, body) of the --notify-title-change notification
+  pub fn title_changed(
+    self,
+    room_id: i64,
+    name: &str,
+    previous: &str,
+    title: &str,
+  ) -> (String, String) {
+    let summary = match self {
+      Self::En => "Title changed",
+      Self::Zh => "直播间标题已更改",
+    };
+    (
+      summary.to_string(),
+      format!("{name} ({room_id})\n{previous} → {title}"),
+    )
+  }
+
+  /// summary of a StreamStarted within --restream-window-secs of the room's
+  /// last StreamEnded, in place of the template's
+  pub fn back_online(self, name: &str) -> String {
+    match self {
+      Self::En => format!("{name} is back online"),
+      Self::Zh => format!("{name} 重新开播了"),
+    }
+  }
+
+  /// appended to the body of a restream notification
+  pub fn offline_for(self, secs: u64) -> String {
+    let (minutes, secs) = (secs / 60, secs % 60);
+    match (self, minutes) {
+      (Self::En, 0) => format!("back after {secs}s offline"),
+      (Self::En, _) => format!("back after {minutes}m {secs}s offline"),
+      (Self::Zh, 0) => format!("下播 {secs} 秒后回来了"),
+      (Self::Zh, _) => format!("下播 {minutes} 分 {secs} 秒后回来了"),
+    }
+  }
+
+  /// (summary, body) of the --watchdog-secs notification
+  pub fn silent(self, secs: u64) -> (String, String) {
+    match self {
+      Self::En => (
+        "No events received".to_string(),
+        format!("no events received in {secs} seconds, is the recorder still sending webhooks?"),
+      ),
+      Self::Zh => (
+        "长时间未收到事件".to_string(),
+        format!("{secs} 秒内没有收到事件, 录播姬还在发送 webhook 吗?"),
+      ),
+    }
+  }
+
+  /// (summary, body) of a digest of stream starts, one line per room
+  pub fn rooms_started(self, rooms: &[String]) -> (String, String) {
+    let count = rooms.len();
+    let summary = match self {
+      Self::En => format!("{count} rooms started"),
+      Self::Zh => format!("{count} 个直播间开播了"),
+    };
+    (summary, rooms.join("\n"))
+  }
+
+  /// (summary, body) of the notification sent after quiet hours, listing the
+  /// summaries of the ones held back
+  pub fn digest(self, summaries: &[String]) -> (String, String) {
+    let count = summaries.len();
+    let summary = match self {
+      Self::En => format!("{count} notifications during quiet hours"),
+      Self::Zh => format!("免打扰期间的 {count} 条通知"),
+    };
+    (summary, summaries.join("\n"))
+  }
+
+  /// (summary, body) of the notification sent when a room's danmaku connection
+  /// drops or comes back
+  pub fn danmaku(self, connected: bool, room_id: i64, name: &str) -> (String, String) {
+    let summary = match (self, connected) {
+      (Self::En, false) => "Danmaku disconnected",
+      (Self::En, true) => "Danmaku reconnected",
+      (Self::Zh, false) => "弹幕连接断开",
+      (Self::Zh, true) => "弹幕已重新连接",
+    };
+    (summary.to_string(), format!("{name} ({room_id})"))
+  }
+
+  /// (summary, body) of the --notify-problems notification for an event type
+  /// it doesn't know
+  pub fn attention(self, event_type: &str, room_id: i64, name: &str) -> (String, String) {
+    match self {
+      Self::En => (
+        "Attention needed".to_string(),
+        format!("{name} ({room_id})\nthe recorder sent an unknown {event_type} event"),
+      ),
+      Self::Zh => (
+        "需要注意".to_string(),
+        format!("{name} ({room_id})\n录播姬发送了未知的 {event_type} 事件"),
+      ),
+    }
+  }
+
+  /// (summary, body) of the notification sent when a room starts being
+  /// recorded, saying whether it's streaming too
+  pub fn recording_started(self, room_id: i64, name: &str, streaming: bool) -> (String, String) {
+    match self {
+      Self::En => (
+        "Recording started".to_string(),
+        format!(
+          "{name} ({room_id})\nrecording: yes, streaming: {}",
+          if streaming { "yes" } else { "no" }
+        ),
+      ),
+      Self::Zh => (
+        "开始录制".to_string(),
+        format!(
+          "{name} ({room_id})\n录制中: 是, 直播中: {}",
+          if streaming { "是" } else { "否" }
+        ),
+      ),
+    }
+  }
+
+  /// (summary, body) of the `--notify-on-start` notification
+  pub fn started(self, ports: &[u16]) -> (&'static str, String) {
+    let ports = ports
+      .iter()
+      .map(|it| format!(":{it}"))
+      .collect::<Vec<_>>()
+      .join(", ");
+    match self {
+      Self::En => (
+        "BiliRecNotifier started",
+        format!("BiliRecNotifier started, listening on {ports}"),
+      ),
+      Self::Zh => (
+        "BiliRecNotifier 已启动",
+        format!("BiliRecNotifier 已启动, 监听端口 {ports}"),
+      ),
+    }
+  }
+
+  /// the tray's first line, when the last webhook came and whether that's
+  /// longer ago than --watchdog-secs
+  pub fn tray_status(self, last: Option<DateTime<FixedOffset>>, silent: bool) -> String {
+    let last = last.map(|it| it.format("%H:%M").to_string());
+    match (self, last, silent) {
+      (Self::En, None, false) => "Waiting for webhooks".to_string(),
+      (Self::En, None, true) => "No webhooks since startup".to_string(),
+      (Self::En, Some(last), false) => format!("Last webhook at {last}"),
+      (Self::En, Some(last), true) => format!("No webhooks since {last}"),
+      (Self::Zh, None, false) => "等待 webhook".to_string(),
+      (Self::Zh, None, true) => "启动以来未收到 webhook".to_string(),
+      (Self::Zh, Some(last), false) => format!("最近的 webhook: {last}"),
+      (Self::Zh, Some(last), true) => format!("{last} 以来未收到 webhook"),
+    }
+  }
+
+  pub fn tray_muted(self, until: DateTime<Local>) -> String {
+    let until = until.format("%H:%M");
+    match self {
+      Self::En => format!("Muted until {until}"),
+      Self::Zh => format!("静音至 {until}"),
+    }
+  }
+
+  /// the tray's (no events, mute, quit) menu items
+  pub fn tray_menu(self) -> (&'static str, &'static str, &'static str) {
+    match self {
+      Self::En => ("No events yet", "Mute for an hour", "Quit"),
+      Self::Zh => ("暂无事件", "静音一小时", "退出"),
+    }
+  }
+}
@@ -0,0 +1,195 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::Deserialize;
+use tracing::{info, warn};
+
+/// for looking up and downloading one image, the notification waits on it
+static TIMEOUT: Duration = Duration::from_secs(3);
+
+/// streamer avatars and live covers, downloaded at most once per `ttl` and
+/// kept on disk
+pub struct ImageCache {
+  client: reqwest::Client,
+  dir: PathBuf,
+  ttl: Duration,
+  pub avatars: bool,
+  pub covers: bool,
+}
+
+#[derive(Deserialize)]
+struct ApiResponse<T> {
+  code: i64,
+  #[serde(default)]
+  message: String,
+  data: Option<T>,
+}
+
+#[derive(Deserialize)]
+struct AnchorInRoom {
+  info: AnchorInfo,
+}
+
+#[derive(Deserialize)]
+struct AnchorInfo {
+  face: String,
+}
+
+#[derive(Deserialize)]
+struct RoomInfo {
+  user_cover: String,
+}
+
+impl ImageCache {
+  pub fn new(ttl: Duration, avatars: bool, covers: bool) -> Self {
+    Self {
+      client: reqwest::Client::new(),
+      dir: std::env::temp_dir().join("bilibili_rec_notifier"),
+      ttl,
+      avatars,
+      covers,
+    }
+  }
+
+  /// local path of the avatar of the room's streamer, `None` if it's turned off
+  /// or can't be had in time
+  pub async fn avatar(&self, room_id: i64) -> Option<PathBuf> {
+    if !self.avatars {
+      return None;
+    }
+    self
+      .get("avatars", room_id, async {
+        let res = self
+          .api::<AnchorInRoom>(
+            "https://api.live.bilibili.com/live_user/v1/UserInfo/get_anchor_in_room",
+            ("roomid", room_id),
+          )
+          .await?;
+        Ok(res.info.face)
+      })
+      .await
+  }
+
+  /// local path of the room's live cover, like [`Self::avatar`]
+  pub async fn cover(&self, room_id: i64) -> Option<PathBuf> {
+    if !self.covers {
+      return None;
+    }
+    self
+      .get("covers", room_id, async {
+        let res = self
+          .api::<RoomInfo>(
+            "https://api.live.bilibili.com/room/v1/Room/get_info",
+            ("room_id", room_id),
+          )
+          .await?;
+        Ok(res.user_cover)
+      })
+      .await
+  }
+
+  /// remove images past their ttl, so the cache doesn't grow with every room
+  /// ever seen
+  pub async fn clean(&self) {
+    let mut removed = 0;
+    for kind in ["avatars", "covers"] {
+      let Ok(mut entries) = tokio::fs::read_dir(self.dir.join(kind)).await else {
+        continue;
+      };
+      while let Ok(Some(entry)) = entries.next_entry().await {
+        if !self.is_fresh(&entry.path()).await && tokio::fs::remove_file(entry.path()).await.is_ok()
+        {
+          removed += 1;
+        }
+      }
+    }
+    if removed > 0 {
+      info!("removed {removed} stale cached images");
+    }
+  }
+
+  async fn get<F>(&self, kind: &str, room_id: i64, url: F) -> Option<PathBuf>
+  where
+    F: std::future::Future<Output = Result<String, String>>,
+  {
+    let path = self.dir.join(kind).join(room_id.to_string());
+    if self.is_fresh(&path).await {
+      return Some(path);
+    }
+
+    let download = async {
+      let url = url.await?;
+      self.download(&url, &path).await
+    };
+    match tokio::time::timeout(TIMEOUT, download).await {
+      Ok(Ok(())) => Some(path),
+      Ok(Err(err)) => {
+        warn!("failed to fetch {kind} of {room_id}\n{err}");
+        None
+      }
+      Err(_) => {
+        warn!("fetching {kind} of {room_id} timed out");
+        None
+      }
+    }
+  }
+
+  async fn is_fresh(&self, path: &Path) -> bool {
+    tokio::fs::metadata(path)
+      .await
+      .and_then(|it| it.modified())
+      .is_ok_and(|it| {
+        SystemTime::now()
+          .duration_since(it)
+          .is_ok_and(|age| age < self.ttl)
+      })
+  }
+
+  async fn api<T: serde::de::DeserializeOwned>(
+    &self,
+    url: &str,
+    query: (&str, i64),
+  ) -> Result<T, String> {
+    let res = self
+      .client
+      .get(url)
+      .query(&[query])
+      .send()
+      .await
+      .map_err(|err| err.to_string())?
+      .json::<ApiResponse<T>>()
+      .await
+      .map_err(|err| err.to_string())?;
+    match res.data {
+      Some(data) if res.code == 0 => Ok(data),
+      _ => Err(format!("{}: {}", res.code, res.message)),
+    }
+  }
+
+  async fn download(&self, url: &str, path: &Path) -> Result<(), String> {
+    let image = self
+      .client
+      .get(url)
+      .send()
+      .await
+      .and_then(|it| it.error_for_status())
+      .map_err(|err| err.to_string())?
+      .bytes()
+      .await
+      .map_err(|err| err.to_string())?;
+
+    // written aside first, so a cut off download never passes for the image
+    let partial = path.with_extension("part");
+    if let Some(dir) = path.parent() {
+      tokio::fs::create_dir_all(dir)
+        .await
+        .map_err(|err| err.to_string())?;
+    }
+    tokio::fs::write(&partial, &image)
+      .await
+      .map_err(|err| err.to_string())?;
+    tokio::fs::rename(&partial, path)
+      .await
+      .map_err(|err| err.to_string())
+  }
+}
@@ -0,0 +1,102 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{SecondsFormat, Utc};
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::Subscriber;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::registry::LookupSpan;
+
+/// how log lines are written, `--log-format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+  Text,
+  /// an object a line, for log collectors
+  Json,
+}
+
+impl FromStr for LogFormat {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "text" => Ok(Self::Text),
+      "json" => Ok(Self::Json),
+      _ => Err(format!("unknown log format `{s}`, expected text or json")),
+    }
+  }
+}
+
+/// a log line as an object of `timestamp`, `level`, `target`, `message` and
+/// the event's fields, like `status` of the request log
+pub struct Json;
+
+impl<S, N> FormatEvent<S, N> for Json
+where
+  S: Subscriber + for<'a> LookupSpan<'a>,
+  N: for<'a> FormatFields<'a> + 'static,
+{
+  fn format_event(
+    &self,
+    _ctx: &FmtContext<'_, S, N>,
+    mut writer: Writer<'_>,
+    event: &tracing::Event<'_>,
+  ) -> fmt::Result {
+    let metadata = event.metadata();
+    let mut line = Map::new();
+    line.insert(
+      "timestamp".to_string(),
+      Utc::now()
+        .to_rfc3339_opts(SecondsFormat::Micros, true)
+        .into(),
+    );
+    line.insert("level".to_string(), metadata.level().as_str().into());
+    line.insert("target".to_string(), metadata.target().into());
+
+    let mut fields = Fields(Map::new());
+    event.record(&mut fields);
+    line.insert(
+      "message".to_string(),
+      fields.0.remove("message").unwrap_or_default(),
+    );
+    // they don't replace the ones above
+    for (name, value) in fields.0 {
+      line.entry(name).or_insert(value);
+    }
+
+    writeln!(writer, "{}", Value::Object(line))
+  }
+}
+
+struct Fields(Map<String, Value>);
+
+impl Visit for Fields {
+  fn record_f64(&mut self, field: &Field, value: f64) {
+    self.0.insert(field.name().to_string(), value.into());
+  }
+
+  fn record_i64(&mut self, field: &Field, value: i64) {
+    self.0.insert(field.name().to_string(), value.into());
+  }
+
+  fn record_u64(&mut self, field: &Field, value: u64) {
+    self.0.insert(field.name().to_string(), value.into());
+  }
+
+  fn record_bool(&mut self, field: &Field, value: bool) {
+    self.0.insert(field.name().to_string(), value.into());
+  }
+
+  fn record_str(&mut self, field: &Field, value: &str) {
+    self.0.insert(field.name().to_string(), value.into());
+  }
+
+  // the message, and fields logged with `%` or `?`
+  fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+    self
+      .0
+      .insert(field.name().to_string(), format!("{value:?}").into());
+  }
+}
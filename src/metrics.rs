@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::desktop;
+
+/// counters for /metrics and /healthz
+#[derive(Default)]
+pub struct Metrics {
+  /// webhooks with a parsable event
+  pub events: AtomicU64,
+  /// shown on the desktop
+  pub notified: AtomicU64,
+  /// failed to show on the desktop
+  pub notify_failures: AtomicU64,
+  /// attempts to send failed notifications again, through any notifier
+  pub retries: AtomicU64,
+  /// notifications given up on, through any notifier
+  pub permanent_failures: AtomicU64,
+  /// held back by --max-notifications
+  pub rate_limited: AtomicU64,
+  /// sent through a notifier besides the desktop
+  pub remote_sent: AtomicU64,
+  /// failed to send through a notifier besides the desktop
+  pub remote_failures: AtomicU64,
+  /// sent and failed by notifier name, the desktop included
+  pub by_notifier: Mutex<BTreeMap<&'static str, (u64, u64)>>,
+}
+
+impl Metrics {
+  pub fn inc(counter: &AtomicU64) {
+    counter.fetch_add(1, Ordering::Relaxed);
+  }
+
+  /// count a send through a notifier, the desktop's aren't remote
+  pub fn sent_through(&self, notifier: &'static str, ok: bool) {
+    let mut by_notifier = self.by_notifier.lock().unwrap();
+    let (sent, failed) = by_notifier.entry(notifier).or_default();
+    let remote = notifier != desktop::NAME;
+    match ok {
+      true => {
+        *sent += 1;
+        if remote {
+          Self::inc(&self.remote_sent);
+        }
+      }
+      false => {
+        *failed += 1;
+        if remote {
+          Self::inc(&self.remote_failures);
+        }
+      }
+    }
+  }
+
+  /// in the prometheus text format
+  pub fn render(&self) -> String {
+    let counters = [
+      ("events_received", "events received", &self.events),
+      (
+        "notifications_shown",
+        "desktop notifications shown",
+        &self.notified,
+      ),
+      (
+        "notification_failures",
+        "desktop notifications that failed to show",
+        &self.notify_failures,
+      ),
+      (
+        "notification_retries",
+        "attempts to send failed notifications again through any notifier",
+        &self.retries,
+      ),
+      (
+        "notification_permanent_failures",
+        "notifications given up on through any notifier",
+        &self.permanent_failures,
+      ),
+      (
+        "notifications_rate_limited",
+        "notifications suppressed by --max-notifications",
+        &self.rate_limited,
+      ),
+      (
+        "remote_notifications_sent",
+        "notifications sent through notifiers besides the desktop",
+        &self.remote_sent,
+      ),
+      (
+        "remote_notification_failures",
+        "notifications that failed to send through notifiers besides the desktop",
+        &self.remote_failures,
+      ),
+    ];
+    let mut out = counters
+      .iter()
+      .map(|(name, help, value)| {
+        format!(
+          "# HELP bilibili_rec_notifier_{name}_total {help}\n\
+           # TYPE bilibili_rec_notifier_{name}_total counter\n\
+           bilibili_rec_notifier_{name}_total {}\n",
+          value.load(Ordering::Relaxed)
+        )
+      })
+      .collect::<String>();
+
+    let by_notifier = self.by_notifier.lock().unwrap();
+    if !by_notifier.is_empty() {
+      out += "# HELP bilibili_rec_notifier_notifier_sends_total sends through each notifier\n\
+              # TYPE bilibili_rec_notifier_notifier_sends_total counter\n";
+      for (notifier, (sent, failed)) in by_notifier.iter() {
+        out += &format!(
+          "bilibili_rec_notifier_notifier_sends_total{{notifier=\"{notifier}\",result=\"sent\"}} {sent}\n\
+           bilibili_rec_notifier_notifier_sends_total{{notifier=\"{notifier}\",result=\"failed\"}} {failed}\n"
+        );
+      }
+    }
+    out
+  }
+
+  /// the body of /healthz
+  pub fn health(&self) -> serde_json::Value {
+    serde_json::json!({
+      "status": "ok",
+      "events": self.events.load(Ordering::Relaxed),
+      "notified": self.notified.load(Ordering::Relaxed),
+      "notify_failures": self.notify_failures.load(Ordering::Relaxed),
+      "retries": self.retries.load(Ordering::Relaxed),
+      "permanent_failures": self.permanent_failures.load(Ordering::Relaxed),
+      "rate_limited": self.rate_limited.load(Ordering::Relaxed),
+      "remote_sent": self.remote_sent.load(Ordering::Relaxed),
+      "remote_failures": self.remote_failures.load(Ordering::Relaxed),
+      "notifiers": self
+        .by_notifier
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, (sent, failed))| (name.to_string(), serde_json::json!({ "sent": sent, "failed": failed })))
+        .collect::<serde_json::Map<_, _>>(),
+    })
+  }
+}
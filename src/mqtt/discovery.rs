@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::mqtt::{Publish, AVAILABILITY_TOPIC};
+use crate::Event;
+
+/// between the discovery prefix and the room id of config topics
+static NODE_ID: &str = "bilibili_rec_notifier";
+
+/// a home assistant binary sensor for each room, on while it streams
+#[derive(Debug)]
+pub struct Discovery {
+  /// like `homeassistant`
+  pub prefix: String,
+  /// of the room filters, `None` when every room passes, sensors of rooms
+  /// that aren't in it anymore are removed
+  pub rooms: Option<Vec<i64>>,
+}
+
+pub(super) struct Sensors {
+  discovery: Discovery,
+  topic_prefix: String,
+  /// rooms an event came from, with the name of the streamer
+  named: Mutex<HashMap<i64, String>>,
+}
+
+impl Sensors {
+  pub(super) fn new(discovery: Discovery, topic_prefix: String) -> Self {
+    Self {
+      discovery,
+      topic_prefix,
+      named: Default::default(),
+    }
+  }
+
+  /// the topic filter to find existing sensors with
+  pub(super) fn subscription(&self) -> String {
+    format!("{}/binary_sensor/{NODE_ID}/+/config", self.discovery.prefix)
+  }
+
+  fn config_topic(&self, room_id: i64) -> String {
+    format!(
+      "{}/binary_sensor/{NODE_ID}/{room_id}/config",
+      self.discovery.prefix
+    )
+  }
+
+  fn config(&self, room_id: i64, name: Option<&str>) -> Publish {
+    let prefix = &self.topic_prefix;
+    let name = match name {
+      Some(name) => format!("{name} live"),
+      None => format!("Room {room_id} live"),
+    };
+    let config = serde_json::json!({
+      "name": name,
+      "unique_id": format!("{NODE_ID}_{room_id}"),
+      "state_topic": format!("{prefix}{room_id}/live"),
+      "json_attributes_topic": format!("{prefix}{room_id}/attributes"),
+      "availability_topic": format!("{prefix}{AVAILABILITY_TOPIC}"),
+      "payload_on": "ON",
+      "payload_off": "OFF",
+      "payload_available": "online",
+      "payload_not_available": "offline",
+      "icon": "mdi:television-play",
+    });
+    Publish {
+      topic: self.config_topic(room_id),
+      payload: config.to_string().into_bytes(),
+      retain: true,
+    }
+  }
+
+  /// sent on every connect, for the rooms of the filter and those seen since
+  pub(super) fn configs(&self) -> Vec<Publish> {
+    let named = self.named.lock().unwrap();
+    let unnamed = self.discovery.rooms.iter().flatten();
+    unnamed
+      .filter(|it| !named.contains_key(it))
+      .map(|it| self.config(*it, None))
+      .chain(named.iter().map(|(id, name)| self.config(*id, Some(name))))
+      .collect()
+  }
+
+  /// the sensor's config the first time the room is seen, its state on a start
+  /// or an end and its attributes
+  pub(super) fn on_event(&self, event: &Event) -> Vec<Publish> {
+    let data = &event.event_data;
+    let prefix = &self.topic_prefix;
+    let mut publishes = vec![];
+
+    let mut named = self.named.lock().unwrap();
+    if named.get(&data.room_id) != Some(&data.name) {
+      named.insert(data.room_id, data.name.clone());
+      publishes.push(self.config(data.room_id, Some(&data.name)));
+    }
+    drop(named);
+
+    let live = match event.event_type.as_str() {
+      "StreamStarted" => Some("ON"),
+      "StreamEnded" => Some("OFF"),
+      _ => None,
+    };
+    if let Some(live) = live {
+      publishes.push(Publish {
+        topic: format!("{prefix}{}/live", data.room_id),
+        payload: live.as_bytes().to_vec(),
+        retain: true,
+      });
+    }
+    let attributes = serde_json::json!({
+      "name": data.name,
+      "title": data.title,
+      "area_parent": data.area_name_parent,
+      "area_child": data.area_name_child,
+    });
+    publishes.push(Publish {
+      topic: format!("{prefix}{}/attributes", data.room_id),
+      payload: attributes.to_string().into_bytes(),
+      retain: true,
+    });
+    publishes
+  }
+
+  /// an empty retained config, which removes the sensor, for a room that was
+  /// discovered before but isn't in the filter anymore
+  pub(super) fn stale(&self, topic: &str, payload: &[u8]) -> Option<Publish> {
+    let rooms = self.discovery.rooms.as_ref()?;
+    if payload.is_empty() {
+      return None;
+    }
+    let room_id = topic
+      .strip_prefix(&format!(
+        "{}/binary_sensor/{NODE_ID}/",
+        self.discovery.prefix
+      ))?
+      .strip_suffix("/config")?
+      .parse::<i64>()
+      .ok()?;
+    if rooms.contains(&room_id) || self.named.lock().unwrap().contains_key(&room_id) {
+      return None;
+    }
+    Some(Publish {
+      topic: topic.to_string(),
+      payload: vec![],
+      retain: true,
+    })
+  }
+}
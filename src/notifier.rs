@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::future::join_all;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use tracing::{error, info, warn};
+
+use crate::config::NotifierConfig;
+use crate::desktop;
+use crate::metrics::Metrics;
+use crate::Event;
+
+pub use crate::notifier::bark::BarkNotifier;
+pub use crate::notifier::breaker::CircuitBreaker;
+pub use crate::notifier::discord::DiscordNotifier;
+pub use crate::notifier::gotify::{EventPriority, GotifyNotifier};
+pub use crate::notifier::matrix::MatrixNotifier;
+#[cfg(test)]
+pub use crate::notifier::mock::MockNotifier;
+pub use crate::notifier::ntfy::{NtfyAuth, NtfyNotifier};
+pub use crate::notifier::pushover::{PushoverNotifier, RoomPriority};
+pub use crate::notifier::serverchan::ServerChanNotifier;
+pub use crate::notifier::slack::SlackNotifier;
+pub use crate::notifier::smtp::{SmtpNotifier, SmtpOptions, SmtpTls};
+pub use crate::notifier::telegram::TelegramNotifier;
+
+mod bark;
+mod breaker;
+mod discord;
+mod gotify;
+mod matrix;
+#[cfg(test)]
+mod mock;
+mod ntfy;
+mod pushover;
+mod serverchan;
+mod slack;
+mod smtp;
+mod telegram;
+
+/// every notifier there is, the desktop included when built with it, as named
+/// in the config file
+pub static NAMES: &[&str] = &[
+  #[cfg(feature = "desktop-notifications")]
+  crate::desktop::NAME,
+  "ntfy",
+  "telegram",
+  "discord",
+  "slack",
+  "bark",
+  "serverchan",
+  "gotify",
+  "pushover",
+  "matrix",
+  "smtp",
+];
+
+/// how long a remote notifier gets for one request
+pub static TIMEOUT: Duration = Duration::from_secs(10);
+
+/// times [`retrying`] tries again
+static RETRIES: u32 = 2;
+
+/// the longest [`retrying`] waits, like on a Retry-After
+static MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// a rendered notification
+#[derive(Debug, Clone)]
+pub struct Message {
+  pub event_type: Option<String>,
+  pub summary: String,
+  pub body: String,
+  /// the live room
+  pub url: Option<String>,
+  /// for per notifier room filters, `None` passes all of them
+  pub room_id: Option<i64>,
+  /// the event it's about, for notifiers with a layout of their own
+  pub event: Option<Event>,
+  /// shown as critical on the desktop, whatever --urgency says
+  #[cfg_attr(
+    not(all(feature = "desktop-notifications", unix, not(target_os = "macos"))),
+    allow(dead_code)
+  )]
+  pub urgent: bool,
+}
+
+/// somewhere to send notifications to, the desktop being one
+#[async_trait]
+pub trait Notifier: Send + Sync {
+  fn name(&self) -> &'static str;
+
+  async fn send(&self, message: &Message) -> Result<(), String>;
+
+  /// send what's held back now, before exiting
+  async fn flush(&self) {}
+}
+
+/// send through every notifier but the `skipped` ones the message passes the
+/// filter of at once, what each one made of it is returned, failures but the
+/// desktop's are logged here
+pub async fn send_all(
+  notifiers: &[Box<dyn Notifier>],
+  skipped: &[String],
+  filters: &HashMap<String, NotifierConfig>,
+  metrics: &Metrics,
+  message: &Message,
+) -> Vec<(&'static str, Result<(), String>)> {
+  let wanted = notifiers
+    .iter()
+    .filter(|it| !skipped.iter().any(|name| name == it.name()))
+    .filter(|it| passes(filters.get(it.name()), message));
+  join_all(wanted.map(|notifier| async move {
+    let result = notifier.send(message).await;
+    metrics.sent_through(notifier.name(), result.is_ok());
+    match &result {
+      Ok(()) => info!("sent to {}", notifier.name()),
+      Err(_) if notifier.name() == desktop::NAME => {}
+      Err(err) => error!("failed to send to {}\n{err}", notifier.name()),
+    }
+    (notifier.name(), result)
+  }))
+  .await
+}
+
+/// send a request, again on a 5xx or 429 after as long as its Retry-After, or
+/// the `retry_after` seconds in its json body, ask, and with backoff when it
+/// doesn't get through at all, a non success response is an error with its body
+pub async fn send_retrying(req: RequestBuilder) -> Result<Response, String> {
+  let mut next = Some(req);
+  retrying(|backoff| {
+    // a request whose body can't be cloned is only sent once
+    let req = next.take().expect("only retried with a clone left");
+    next = req.try_clone();
+    let again = next.is_some();
+    async move {
+      let res = match req.send().await {
+        Ok(it) => it,
+        Err(err) => {
+          let wait = Some(backoff).filter(|_| again && (err.is_connect() || err.is_timeout()));
+          return Err((err.to_string(), wait));
+        }
+      };
+      let status = res.status();
+      if status.is_success() {
+        return Ok(res);
+      }
+
+      let retryable = status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS;
+      let header = res
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|it| it.to_str().ok())
+        .and_then(|it| it.trim().parse::<f64>().ok());
+      let body = res.text().await.unwrap_or_default();
+      let wait = header
+        .or_else(|| retry_after(&body))
+        .and_then(|it| Duration::try_from_secs_f64(it).ok())
+        .unwrap_or(backoff);
+      Err((
+        format!("{status}: {body}"),
+        Some(wait).filter(|_| again && retryable),
+      ))
+    }
+  })
+  .await
+}
+
+/// call `attempt` again, up to [`RETRIES`] times, after the wait its error asks
+/// for, never when that's `None`, it's given the backoff, doubling from a
+/// second, to ask for when it knows no better, every remote notifier retries
+/// through this
+pub async fn retrying<T, F, Fut>(mut attempt: F) -> Result<T, String>
+where
+  F: FnMut(Duration) -> Fut,
+  Fut: Future<Output = Result<T, (String, Option<Duration>)>>,
+{
+  let mut tries = 0;
+  loop {
+    let backoff = Duration::from_secs(1 << tries);
+    match attempt(backoff).await {
+      Err((err, Some(wait))) if tries < RETRIES && wait <= MAX_RETRY_AFTER => {
+        warn!("{err}, trying again in {wait:?}");
+        tokio::time::sleep(wait).await;
+        tries += 1;
+      }
+      result => return result.map_err(|(err, _)| err),
+    }
+  }
+}
+
+/// like discord's `{"retry_after": 1.5}` and telegram's
+/// `{"parameters": {"retry_after": 3}}`
+fn retry_after(body: &str) -> Option<f64> {
+  let body = serde_json::from_str::<serde_json::Value>(body).ok()?;
+  body
+    .get("retry_after")
+    .or_else(|| body.pointer("/parameters/retry_after"))?
+    .as_f64()
+}
+
+/// whether a message gets through a notifier's room and event type filters
+pub fn passes(filter: Option<&NotifierConfig>, message: &Message) -> bool {
+  let Some(filter) = filter else {
+    return true;
+  };
+  let room = match (&filter.rooms, message.room_id) {
+    (Some(rooms), Some(room_id)) => rooms.contains(room_id),
+    _ => true,
+  };
+  let event = match (&filter.events, &message.event_type) {
+    (Some(events), Some(event_type)) => events.contains(event_type),
+    _ => true,
+  };
+  room && event
+}
+
+#[cfg(test)]
+mod tests {
+  use std::str::FromStr;
+
+  use super::*;
+  use crate::filter::RoomFilter;
+
+  fn message(room_id: i64) -> Message {
+    Message {
+      event_type: Some("StreamStarted".to_string()),
+      summary: "summary".to_string(),
+      body: "body".to_string(),
+      url: None,
+      room_id: Some(room_id),
+      event: None,
+      urgent: false,
+    }
+  }
+
+  #[tokio::test]
+  async fn fans_out_through_the_filters() {
+    let (desktop, ntfy, telegram) = (
+      MockNotifier::named(desktop::NAME),
+      MockNotifier::named("ntfy"),
+      MockNotifier::named("telegram"),
+    );
+    telegram.set_failing(true);
+    let notifiers: Vec<Box<dyn Notifier>> = vec![
+      Box::new(desktop.clone()),
+      Box::new(ntfy.clone()),
+      Box::new(telegram.clone()),
+    ];
+    let filters = HashMap::from([(
+      "ntfy".to_string(),
+      NotifierConfig {
+        rooms: Some(RoomFilter::from_str("1-3").unwrap()),
+        events: None,
+      },
+    )]);
+    let metrics = Metrics::default();
+
+    let results = send_all(&notifiers, &[], &filters, &metrics, &message(5)).await;
+    assert_eq!(
+      results,
+      [
+        (desktop::NAME, Ok(())),
+        ("telegram", Err("failing".to_string()))
+      ]
+    );
+    let results = send_all(
+      &notifiers,
+      &["telegram".to_string()],
+      &filters,
+      &metrics,
+      &message(2),
+    )
+    .await;
+    assert_eq!(results, [(desktop::NAME, Ok(())), ("ntfy", Ok(()))]);
+
+    assert_eq!(desktop.sent().len(), 2);
+    assert_eq!(ntfy.sent().len(), 1);
+    let by_notifier = metrics.by_notifier.lock().unwrap();
+    assert_eq!(by_notifier[desktop::NAME], (2, 0));
+    assert_eq!(by_notifier["ntfy"], (1, 0));
+    assert_eq!(by_notifier["telegram"], (0, 1));
+    assert_eq!(
+      metrics
+        .remote_sent
+        .load(std::sync::atomic::Ordering::Relaxed),
+      1
+    );
+  }
+}
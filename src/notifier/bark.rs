@@ -0,0 +1,45 @@
+use async_trait::async_trait;
+
+use crate::notifier::{self, Message, Notifier, TIMEOUT};
+
+/// pushes to an iphone through bark
+pub struct BarkNotifier {
+  client: reqwest::Client,
+  url: String,
+  group: Option<String>,
+}
+
+impl BarkNotifier {
+  /// `url` is the device url, like `https://api.day.app/<key>`
+  pub fn new(url: &str, group: Option<String>) -> Self {
+    Self {
+      client: reqwest::Client::new(),
+      url: url.trim_end_matches('/').to_string(),
+      group,
+    }
+  }
+}
+
+#[async_trait]
+impl Notifier for BarkNotifier {
+  fn name(&self) -> &'static str {
+    "bark"
+  }
+
+  /// posted as json rather than in the path, so titles need no escaping
+  async fn send(&self, message: &Message) -> Result<(), String> {
+    let mut push = serde_json::json!({
+      "title": message.summary,
+      "body": message.body,
+    });
+    if let Some(url) = &message.url {
+      push["url"] = url.as_str().into();
+    }
+    if let Some(group) = &self.group {
+      push["group"] = group.as_str().into();
+    }
+
+    let req = self.client.post(&self.url).timeout(TIMEOUT).json(&push);
+    notifier::send_retrying(req).await.map(|_| ())
+  }
+}
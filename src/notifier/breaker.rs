@@ -0,0 +1,197 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use tracing::{info, warn};
+
+use crate::notifier::{Message, Notifier};
+
+/// stops trying a notifier for `cooldown` after `threshold` failures in a row,
+/// then lets one message through to test it
+pub struct CircuitBreaker {
+  inner: Box<dyn Notifier>,
+  threshold: u32,
+  cooldown: Duration,
+  state: Mutex<State>,
+}
+
+#[derive(Clone, Copy)]
+enum State {
+  Closed {
+    failures: u32,
+  },
+  Open {
+    until: Instant,
+  },
+  /// a test message is on its way
+  HalfOpen,
+}
+
+impl CircuitBreaker {
+  pub fn new(inner: Box<dyn Notifier>, threshold: u32, cooldown: Duration) -> Self {
+    Self {
+      inner,
+      threshold: threshold.max(1),
+      cooldown,
+      state: Mutex::new(State::Closed { failures: 0 }),
+    }
+  }
+
+  fn failed(&self) {
+    let mut state = self.state.lock().unwrap();
+    let name = self.inner.name();
+    *state = match *state {
+      State::Closed { failures } if failures + 1 < self.threshold => State::Closed {
+        failures: failures + 1,
+      },
+      State::Closed { .. } => {
+        warn!("{name} circuit open after {} failures", self.threshold);
+        State::Open {
+          until: Instant::now() + self.cooldown,
+        }
+      }
+      State::HalfOpen | State::Open { .. } => {
+        warn!("{name} circuit open again, test failed");
+        State::Open {
+          until: Instant::now() + self.cooldown,
+        }
+      }
+    };
+  }
+
+  fn succeeded(&self) {
+    let mut state = self.state.lock().unwrap();
+    if let State::HalfOpen | State::Open { .. } = *state {
+      info!("{} circuit closed", self.inner.name());
+    }
+    *state = State::Closed { failures: 0 };
+  }
+}
+
+#[async_trait]
+impl Notifier for CircuitBreaker {
+  fn name(&self) -> &'static str {
+    self.inner.name()
+  }
+
+  async fn send(&self, message: &Message) -> Result<(), String> {
+    let mut trial = None;
+    {
+      let mut state = self.state.lock().unwrap();
+      match *state {
+        State::Closed { .. } => {}
+        State::Open { until } if Instant::now() < until => {
+          return Err("circuit open, skipped".to_string());
+        }
+        State::Open { .. } => {
+          info!("{} circuit half-open, testing", self.inner.name());
+          *state = State::HalfOpen;
+          trial = Some(Trial(self));
+        }
+        State::HalfOpen => return Err("circuit half-open, skipped while testing".to_string()),
+      }
+    }
+
+    let result = self.inner.send(message).await;
+    std::mem::forget(trial);
+    match &result {
+      Ok(()) => self.succeeded(),
+      Err(_) => self.failed(),
+    }
+    result
+  }
+
+  async fn flush(&self) {
+    self.inner.flush().await;
+  }
+}
+
+/// the test message of a half-open circuit, dropped before it's done when the
+/// send is, like on a request timeout, which lets the next message test it
+/// instead of the circuit staying half-open for good
+struct Trial<'a>(&'a CircuitBreaker);
+
+impl Drop for Trial<'_> {
+  fn drop(&mut self) {
+    let mut state = self.0.state.lock().unwrap();
+    if let State::HalfOpen = *state {
+      *state = State::Open {
+        until: Instant::now(),
+      };
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use futures_util::FutureExt;
+
+  use super::*;
+  use crate::notifier::MockNotifier;
+
+  fn message() -> Message {
+    Message {
+      event_type: None,
+      summary: "summary".to_string(),
+      body: "body".to_string(),
+      url: None,
+      room_id: None,
+      event: None,
+      urgent: false,
+    }
+  }
+
+  #[tokio::test]
+  async fn opens_and_closes() {
+    let mock = MockNotifier::default();
+    let breaker = CircuitBreaker::new(Box::new(mock.clone()), 2, Duration::ZERO);
+    mock.set_failing(true);
+    assert!(breaker.send(&message()).await.is_err());
+    assert!(matches!(
+      *breaker.state.lock().unwrap(),
+      State::Closed { failures: 1 }
+    ));
+    assert!(breaker.send(&message()).await.is_err());
+    assert!(matches!(*breaker.state.lock().unwrap(), State::Open { .. }));
+
+    // past the cooldown the next one is the test
+    mock.set_failing(false);
+    assert!(breaker.send(&message()).await.is_ok());
+    assert!(matches!(
+      *breaker.state.lock().unwrap(),
+      State::Closed { failures: 0 }
+    ));
+    assert_eq!(mock.sent().len(), 1);
+  }
+
+  #[tokio::test]
+  async fn skips_while_open() {
+    let mock = MockNotifier::default();
+    let breaker = CircuitBreaker::new(Box::new(mock.clone()), 1, Duration::from_secs(60));
+    mock.set_failing(true);
+    assert!(breaker.send(&message()).await.is_err());
+    mock.set_failing(false);
+    assert_eq!(
+      breaker.send(&message()).await,
+      Err("circuit open, skipped".to_string())
+    );
+    assert!(mock.sent().is_empty());
+  }
+
+  #[tokio::test]
+  async fn dropped_test_lets_the_next_one_test() {
+    let mock = MockNotifier::default();
+    let breaker = CircuitBreaker::new(Box::new(mock.clone()), 1, Duration::ZERO);
+    mock.set_failing(true);
+    assert!(breaker.send(&message()).await.is_err());
+
+    mock.set_hanging(true);
+    assert!(breaker.send(&message()).now_or_never().is_none());
+    assert!(matches!(*breaker.state.lock().unwrap(), State::Open { .. }));
+
+    mock.set_hanging(false);
+    mock.set_failing(false);
+    assert!(breaker.send(&message()).await.is_ok());
+    assert_eq!(mock.sent().len(), 1);
+  }
+}
@@ -0,0 +1,69 @@
+use async_trait::async_trait;
+
+use crate::notifier::{self, Message, Notifier, TIMEOUT};
+use crate::template;
+
+// discord rejects longer embeds with a 400
+static TITLE_LIMIT: usize = 256;
+static DESCRIPTION_LIMIT: usize = 4096;
+static FIELD_LIMIT: usize = 1024;
+
+/// posts an embed to a discord channel webhook
+pub struct DiscordNotifier {
+  client: reqwest::Client,
+  url: String,
+}
+
+impl DiscordNotifier {
+  pub fn new(url: &str) -> Self {
+    Self {
+      client: reqwest::Client::new(),
+      url: url.to_string(),
+    }
+  }
+}
+
+#[async_trait]
+impl Notifier for DiscordNotifier {
+  fn name(&self) -> &'static str {
+    "discord"
+  }
+
+  async fn send(&self, message: &Message) -> Result<(), String> {
+    let req = self
+      .client
+      .post(&self.url)
+      .timeout(TIMEOUT)
+      .json(&serde_json::json!({ "embeds": [embed(message)] }));
+    notifier::send_retrying(req).await.map(|_| ())
+  }
+}
+
+/// the streamer as the title and the stream title as the description for
+/// events, the rendered summary and body for everything else
+fn embed(message: &Message) -> serde_json::Value {
+  let (title, description, area) = match &message.event {
+    Some(event) => {
+      let data = &event.event_data;
+      let area = format!("{} · {}", data.area_name_parent, data.area_name_child);
+      (data.name.as_str(), data.title.as_str(), Some(area))
+    }
+    None => (message.summary.as_str(), message.body.as_str(), None),
+  };
+
+  let mut embed = serde_json::json!({ "title": template::truncate(title, TITLE_LIMIT) });
+  // an empty one is a 400 too
+  if !description.is_empty() {
+    embed["description"] = template::truncate(description, DESCRIPTION_LIMIT).into();
+  }
+  if let Some(url) = &message.url {
+    embed["url"] = url.as_str().into();
+  }
+  if let Some(area) = area {
+    embed["fields"] = serde_json::json!([{
+      "name": "Area",
+      "value": template::truncate(&area, FIELD_LIMIT),
+    }]);
+  }
+  embed
+}
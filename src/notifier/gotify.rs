@@ -0,0 +1,106 @@
+use std::str::FromStr;
+
+use async_trait::async_trait;
+
+use crate::notifier::{self, Message, Notifier, TIMEOUT};
+use crate::template;
+
+/// gotify's own default
+static DEFAULT_PRIORITY: u8 = 5;
+
+/// sends messages to a gotify server
+pub struct GotifyNotifier {
+  client: reqwest::Client,
+  url: String,
+  token: String,
+  priorities: Vec<EventPriority>,
+}
+
+/// a `--gotify-priority`, like `StreamStarted=8`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventPriority {
+  pub event_type: String,
+  pub priority: u8,
+}
+
+impl FromStr for EventPriority {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let Some((event_type, priority)) = s.split_once('=') else {
+      return Err(format!(
+        "invalid priority `{s}`, expected like StreamStarted=8"
+      ));
+    };
+    let event_type = event_type.trim();
+    if !template::EVENT_TYPES.contains(&event_type) {
+      return Err(format!("unknown event type `{event_type}`"));
+    }
+    let priority =
+      u8::from_str(priority.trim()).map_err(|_| format!("invalid priority `{priority}`"))?;
+    Ok(Self {
+      event_type: event_type.to_string(),
+      priority,
+    })
+  }
+}
+
+impl GotifyNotifier {
+  /// `insecure` accepts any certificate, for self signed ones
+  pub fn new(
+    url: &str,
+    token: &str,
+    priorities: Vec<EventPriority>,
+    insecure: bool,
+  ) -> Result<Self, String> {
+    let client = reqwest::Client::builder()
+      .danger_accept_invalid_certs(insecure)
+      .build()
+      .map_err(|err| err.to_string())?;
+    Ok(Self {
+      client,
+      url: format!("{}/message", url.trim_end_matches('/')),
+      token: token.to_string(),
+      priorities,
+    })
+  }
+
+  fn priority(&self, event_type: Option<&str>) -> u8 {
+    self
+      .priorities
+      .iter()
+      .find(|it| Some(it.event_type.as_str()) == event_type)
+      .map_or(DEFAULT_PRIORITY, |it| it.priority)
+  }
+}
+
+#[async_trait]
+impl Notifier for GotifyNotifier {
+  fn name(&self) -> &'static str {
+    "gotify"
+  }
+
+  /// the event goes along in the extras for automations
+  async fn send(&self, message: &Message) -> Result<(), String> {
+    let mut extras = serde_json::json!({});
+    if let Some(url) = &message.url {
+      extras["client::notification"] = serde_json::json!({ "click": { "url": url } });
+    }
+    if let Some(event) = &message.event {
+      extras["bilibili_rec_notifier::event"] = serde_json::to_value(event).unwrap();
+    }
+
+    let req = self
+      .client
+      .post(&self.url)
+      .timeout(TIMEOUT)
+      .header("X-Gotify-Key", &self.token)
+      .json(&serde_json::json!({
+        "title": message.summary,
+        "message": message.body,
+        "priority": self.priority(message.event_type.as_deref()),
+        "extras": extras,
+      }));
+    notifier::send_retrying(req).await.map(|_| ())
+  }
+}
@@ -0,0 +1,157 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use async_trait::async_trait;
+use reqwest::Url;
+
+use crate::notifier::{self, Message, Notifier, TIMEOUT};
+
+/// sends messages to a matrix room through the client-server api
+pub struct MatrixNotifier {
+  client: reqwest::Client,
+  /// up to the room, the rest is added per message
+  url: Url,
+  token: String,
+  /// take the summary and body as markdown for the formatted body
+  markdown: bool,
+  /// for transaction ids of messages that aren't about an event
+  sent: AtomicU64,
+}
+
+impl MatrixNotifier {
+  /// `room` is an id like `!abc:example.org`, the token's user has to have
+  /// joined it
+  pub fn new(homeserver: &str, room: &str, token: &str, markdown: bool) -> Result<Self, String> {
+    let mut url = Url::parse(homeserver)
+      .map_err(|err| format!("invalid homeserver url `{homeserver}`\n{err}"))?;
+    url
+      .path_segments_mut()
+      .map_err(|_| format!("invalid homeserver url `{homeserver}`"))?
+      .pop_if_empty()
+      .extend([
+        "_matrix",
+        "client",
+        "v3",
+        "rooms",
+        room,
+        "send",
+        "m.room.message",
+      ]);
+    Ok(Self {
+      client: reqwest::Client::new(),
+      url,
+      token: token.to_string(),
+      markdown,
+      sent: AtomicU64::new(0),
+    })
+  }
+
+  /// the same for every send of an event, the homeserver ignores
+  /// transactions it has seen, so an event the recorder sends again isn't
+  /// posted twice
+  fn transaction_id(&self, message: &Message) -> String {
+    match &message.event {
+      Some(event) => format!(
+        "{}-{}",
+        event.event_id,
+        message.event_type.as_deref().unwrap_or_default()
+      ),
+      None => format!(
+        "{}-{}",
+        chrono::Local::now().timestamp_millis(),
+        self.sent.fetch_add(1, Ordering::Relaxed)
+      ),
+    }
+  }
+}
+
+fn escape(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
+
+/// the little markdown a notification needs to html, `**bold**`, `*italic*`,
+/// `` `code` `` and `[text](url)`, everything else is escaped
+fn markdown(text: &str) -> String {
+  let mut html = String::new();
+  let mut rest = text;
+  while let Some(c) = rest.chars().next() {
+    if let Some((inner, after)) = delimited(rest, "**") {
+      html += &format!("<strong>{}</strong>", markdown(inner));
+      rest = after;
+    } else if let Some((inner, after)) = delimited(rest, "*") {
+      html += &format!("<em>{}</em>", markdown(inner));
+      rest = after;
+    } else if let Some((inner, after)) = delimited(rest, "`") {
+      html += &format!("<code>{}</code>", escape(inner));
+      rest = after;
+    } else if let Some((label, url, after)) = link(rest) {
+      html += &format!("<a href=\"{}\">{}</a>", escape(url), markdown(label));
+      rest = after;
+    } else {
+      html += &escape(&c.to_string());
+      rest = &rest[c.len_utf8()..];
+    }
+  }
+  html.replace('\n', "<br>")
+}
+
+/// what's between `marker` at the start of `text` and the next one, and what
+/// comes after that
+fn delimited<'a>(text: &'a str, marker: &str) -> Option<(&'a str, &'a str)> {
+  let inner = text.strip_prefix(marker)?;
+  let end = inner.find(marker)?;
+  (end > 0).then(|| (&inner[..end], &inner[end + marker.len()..]))
+}
+
+/// the label and url of a link at the start of `text`, and what comes after it
+fn link(text: &str) -> Option<(&str, &str, &str)> {
+  let (label, rest) = text.strip_prefix('[')?.split_once("](")?;
+  let (url, after) = rest.split_once(')')?;
+  let valid = !label.is_empty() && !label.contains('\n') && !url.contains(char::is_whitespace);
+  valid.then_some((label, url, after))
+}
+
+#[async_trait]
+impl Notifier for MatrixNotifier {
+  fn name(&self) -> &'static str {
+    "matrix"
+  }
+
+  async fn send(&self, message: &Message) -> Result<(), String> {
+    let mut body = format!("{}\n{}", message.summary, message.body);
+    let format = match self.markdown {
+      true => markdown,
+      false => |text: &str| escape(text).replace('\n', "<br>"),
+    };
+    let mut html = format!(
+      "<b>{}</b><br>{}",
+      format(&message.summary),
+      format(&message.body)
+    );
+    if let Some(url) = &message.url {
+      body += &format!("\n{url}");
+      html += &format!("<br><a href=\"{}\">{}</a>", escape(url), escape(url));
+    }
+
+    let mut url = self.url.clone();
+    url
+      .path_segments_mut()
+      .unwrap()
+      .push(&self.transaction_id(message));
+    let req = self
+      .client
+      .put(url)
+      .timeout(TIMEOUT)
+      .bearer_auth(&self.token)
+      .json(&serde_json::json!({
+        "msgtype": "m.text",
+        "body": body,
+        "format": "org.matrix.custom.html",
+        "formatted_body": html,
+      }));
+    notifier::send_retrying(req).await.map(|_| ())
+  }
+}
@@ -0,0 +1,60 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+
+use crate::notifier::{Message, Notifier};
+
+/// keeps what it's sent, clones share it
+#[derive(Clone, Default)]
+pub struct MockNotifier(Arc<Inner>);
+
+#[derive(Default)]
+struct Inner {
+  /// "mock" unless given
+  name: Option<&'static str>,
+  sent: Mutex<Vec<Message>>,
+  failing: AtomicBool,
+  /// never done sending
+  hanging: AtomicBool,
+}
+
+impl MockNotifier {
+  /// standing in for the notifier of that name
+  pub fn named(name: &'static str) -> Self {
+    Self(Arc::new(Inner {
+      name: Some(name),
+      ..Default::default()
+    }))
+  }
+
+  pub fn sent(&self) -> Vec<Message> {
+    self.0.sent.lock().unwrap().clone()
+  }
+
+  pub fn set_failing(&self, failing: bool) {
+    self.0.failing.store(failing, Ordering::SeqCst);
+  }
+
+  pub fn set_hanging(&self, hanging: bool) {
+    self.0.hanging.store(hanging, Ordering::SeqCst);
+  }
+}
+
+#[async_trait]
+impl Notifier for MockNotifier {
+  fn name(&self) -> &'static str {
+    self.0.name.unwrap_or("mock")
+  }
+
+  async fn send(&self, message: &Message) -> Result<(), String> {
+    if self.0.hanging.load(Ordering::SeqCst) {
+      std::future::pending::<()>().await;
+    }
+    if self.0.failing.load(Ordering::SeqCst) {
+      return Err("failing".to_string());
+    }
+    self.0.sent.lock().unwrap().push(message.clone());
+    Ok(())
+  }
+}
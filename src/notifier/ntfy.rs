@@ -0,0 +1,94 @@
+use async_trait::async_trait;
+use base64::Engine;
+use reqwest::header::HeaderValue;
+
+use crate::notifier::{self, Message, Notifier, TIMEOUT};
+
+/// publishes to a topic of an ntfy server
+pub struct NtfyNotifier {
+  client: reqwest::Client,
+  url: String,
+  auth: Option<NtfyAuth>,
+}
+
+pub enum NtfyAuth {
+  Basic {
+    user: String,
+    pass: String,
+  },
+  /// an access token
+  Bearer(String),
+}
+
+impl NtfyNotifier {
+  /// `url` is the topic url, like `https://ntfy.sh/topic`
+  pub fn new(url: &str, auth: Option<NtfyAuth>) -> Self {
+    Self {
+      client: reqwest::Client::new(),
+      url: url.trim_end_matches('/').to_string(),
+      auth,
+    }
+  }
+}
+
+#[async_trait]
+impl Notifier for NtfyNotifier {
+  fn name(&self) -> &'static str {
+    "ntfy"
+  }
+
+  async fn send(&self, message: &Message) -> Result<(), String> {
+    let mut req = self
+      .client
+      .post(&self.url)
+      .timeout(TIMEOUT)
+      .header("Title", encode_header(&message.summary))
+      .body(message.body.clone());
+    if let Some(tags) = tags(message.event_type.as_deref()) {
+      req = req.header("Tags", tags);
+    }
+    if let Some(priority) = priority(message.event_type.as_deref()) {
+      req = req.header("Priority", priority);
+    }
+    if let Some(url) = &message.url {
+      req = req.header("Click", url);
+    }
+    req = match &self.auth {
+      Some(NtfyAuth::Basic { user, pass }) => req.basic_auth(user, Some(pass)),
+      Some(NtfyAuth::Bearer(token)) => req.bearer_auth(token),
+      None => req,
+    };
+
+    notifier::send_retrying(req).await.map(|_| ())
+  }
+}
+
+/// stream starts buzz, file events stay quiet, the rest is ntfy's default
+fn priority(event_type: Option<&str>) -> Option<&'static str> {
+  match event_type? {
+    "StreamStarted" => Some("high"),
+    "FileOpening" | "FileClosed" => Some("low"),
+    _ => None,
+  }
+}
+
+/// emoji shortcodes ntfy shows in front of the title
+fn tags(event_type: Option<&str>) -> Option<&'static str> {
+  match event_type? {
+    "StreamStarted" => Some("tv"),
+    "StreamEnded" => Some("stop_sign"),
+    "FileClosed" => Some("floppy_disk"),
+    _ => None,
+  }
+}
+
+/// ntfy decodes RFC 2047 encoded words, which keeps non ascii titles intact
+fn encode_header(value: &str) -> HeaderValue {
+  if value.is_ascii() {
+    if let Ok(it) = HeaderValue::from_str(value) {
+      return it;
+    }
+  }
+  let encoded = base64::engine::general_purpose::STANDARD.encode(value);
+  HeaderValue::from_str(&format!("=?UTF-8?B?{encoded}?=")).expect("base64 is a valid header")
+}
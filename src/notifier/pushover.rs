@@ -0,0 +1,110 @@
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::notifier::{self, Message, Notifier, TIMEOUT};
+use crate::template;
+
+static URL: &str = "https://api.pushover.net/1/messages.json";
+
+/// in chars
+static TITLE_LIMIT: usize = 250;
+static MESSAGE_LIMIT: usize = 1024;
+
+/// sends messages through pushover
+pub struct PushoverNotifier {
+  client: reqwest::Client,
+  token: String,
+  user: String,
+  priorities: Vec<RoomPriority>,
+}
+
+/// a `--pushover-priority`, like `21452505=1`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoomPriority {
+  pub room_id: i64,
+  pub priority: i8,
+}
+
+impl FromStr for RoomPriority {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let Some((room_id, priority)) = s.split_once('=') else {
+      return Err(format!("invalid priority `{s}`, expected like 21452505=1"));
+    };
+    let room_id =
+      i64::from_str(room_id.trim()).map_err(|_| format!("invalid room id `{room_id}`"))?;
+    // 2 is an emergency, which keeps alerting until acknowledged
+    let priority = i8::from_str(priority.trim())
+      .ok()
+      .filter(|it| (-2..=1).contains(it))
+      .ok_or_else(|| format!("invalid priority `{priority}`, expected -2 to 1"))?;
+    Ok(Self { room_id, priority })
+  }
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+  #[serde(default)]
+  errors: Vec<String>,
+}
+
+impl PushoverNotifier {
+  pub fn new(token: &str, user: &str, priorities: Vec<RoomPriority>) -> Self {
+    Self {
+      client: reqwest::Client::new(),
+      token: token.to_string(),
+      user: user.to_string(),
+      priorities,
+    }
+  }
+
+  fn priority(&self, room_id: Option<i64>) -> i8 {
+    self
+      .priorities
+      .iter()
+      .find(|it| Some(it.room_id) == room_id)
+      .map_or(0, |it| it.priority)
+  }
+}
+
+/// the messages in the errors of a response, like `user identifier is invalid`,
+/// instead of the whole body
+fn describe(err: String) -> String {
+  let Some((status, body)) = err.split_once(": ") else {
+    return err;
+  };
+  match serde_json::from_str::<ApiError>(body) {
+    Ok(res) if !res.errors.is_empty() => format!("{status}: {}", res.errors.join(", ")),
+    _ => err,
+  }
+}
+
+#[async_trait]
+impl Notifier for PushoverNotifier {
+  fn name(&self) -> &'static str {
+    "pushover"
+  }
+
+  async fn send(&self, message: &Message) -> Result<(), String> {
+    let mut body = serde_json::json!({
+      "token": self.token,
+      "user": self.user,
+      "title": template::truncate(&message.summary, TITLE_LIMIT),
+      "message": template::truncate(&message.body, MESSAGE_LIMIT),
+      "priority": self.priority(message.room_id),
+    });
+    if let Some(url) = &message.url {
+      body["url"] = url.clone().into();
+      body["url_title"] = "Open room".into();
+    }
+
+    let req = self.client.post(URL).timeout(TIMEOUT).json(&body);
+    notifier::send_retrying(req)
+      .await
+      .map(|_| ())
+      .map_err(describe)
+  }
+}
@@ -0,0 +1,67 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::notifier::{self, Message, Notifier, TIMEOUT};
+
+/// pushes to wechat through Server酱
+pub struct ServerChanNotifier {
+  client: reqwest::Client,
+  url: String,
+}
+
+#[derive(Deserialize)]
+struct ApiResponse {
+  code: i64,
+  #[serde(default)]
+  message: String,
+}
+
+impl ServerChanNotifier {
+  /// Server酱³ keys, `sctp<uid>t...`, have a host of their own, Turbo ones go
+  /// to sctapi.ftqq.com
+  pub fn new(key: &str) -> Self {
+    let uid = key
+      .strip_prefix("sctp")
+      .and_then(|it| it.split_once('t'))
+      .map(|(uid, _)| uid)
+      .filter(|it| !it.is_empty() && it.bytes().all(|it| it.is_ascii_digit()));
+    let url = match uid {
+      Some(uid) => format!("https://{uid}.push.ft07.com/send/{key}.send"),
+      None => format!("https://sctapi.ftqq.com/{key}.send"),
+    };
+    Self {
+      client: reqwest::Client::new(),
+      url,
+    }
+  }
+}
+
+#[async_trait]
+impl Notifier for ServerChanNotifier {
+  fn name(&self) -> &'static str {
+    "serverchan"
+  }
+
+  async fn send(&self, message: &Message) -> Result<(), String> {
+    // desp is markdown, where a line break takes two newlines
+    let mut desp = message.body.replace('\n', "\n\n");
+    if let Some(url) = &message.url {
+      desp += &format!("\n\n[{url}]({url})");
+    }
+    let req = self
+      .client
+      .post(&self.url)
+      .timeout(TIMEOUT)
+      .json(&serde_json::json!({ "title": message.summary, "desp": desp }));
+
+    let res = notifier::send_retrying(req)
+      .await?
+      .json::<ApiResponse>()
+      .await
+      .map_err(|err| err.to_string())?;
+    match res.code {
+      0 => Ok(()),
+      code => Err(format!("{code}: {}", res.message)),
+    }
+  }
+}
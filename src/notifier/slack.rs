@@ -0,0 +1,244 @@
+use async_trait::async_trait;
+use tracing::warn;
+
+use crate::notifier::{self, Message, Notifier, TIMEOUT};
+use crate::template;
+
+// slack rejects longer blocks with a 400 invalid_blocks
+static HEADER_LIMIT: usize = 150;
+static SECTION_LIMIT: usize = 3000;
+
+/// posts a block kit message to a slack incoming webhook
+pub struct SlackNotifier {
+  client: reqwest::Client,
+  url: String,
+}
+
+impl SlackNotifier {
+  pub fn new(url: &str) -> Self {
+    Self {
+      client: reqwest::Client::new(),
+      url: url.to_string(),
+    }
+  }
+
+  async fn post(&self, payload: &serde_json::Value) -> Result<(), String> {
+    let req = self.client.post(&self.url).timeout(TIMEOUT).json(payload);
+    notifier::send_retrying(req).await.map(|_| ())
+  }
+}
+
+#[async_trait]
+impl Notifier for SlackNotifier {
+  fn name(&self) -> &'static str {
+    "slack"
+  }
+
+  async fn send(&self, message: &Message) -> Result<(), String> {
+    let text = text(message);
+    let payload = serde_json::json!({ "text": text, "blocks": blocks(message) });
+    match self.post(&payload).await {
+      // a workspace or client that won't take the blocks still gets the text
+      Err(err) if err.contains("invalid_blocks") => {
+        warn!("slack rejected the blocks, sending the text alone\n{err}");
+        self.post(&serde_json::json!({ "text": text })).await
+      }
+      result => result,
+    }
+  }
+}
+
+/// what's shown in the notification, and instead of the blocks where they
+/// can't be, in slack's mrkdwn with the room linked
+fn text(message: &Message) -> String {
+  let mut text = format!("*{}*\n{}", escape(&message.summary), escape(&message.body));
+  if let Some(url) = &message.url {
+    text += &format!("\n<{url}|Open room>");
+  }
+  text
+}
+
+/// the streamer as the header and the stream title as a section for events,
+/// the rendered summary and body for everything else, with a button to the
+/// live room
+fn blocks(message: &Message) -> serde_json::Value {
+  let (header, section) = match &message.event {
+    Some(event) => {
+      let data = &event.event_data;
+      let section = format!(
+        "{}\n_{} · {}_",
+        escape(&data.title),
+        escape(&data.area_name_parent),
+        escape(&data.area_name_child)
+      );
+      let name = Some(data.name.as_str()).filter(|it| !it.trim().is_empty());
+      (name.unwrap_or(&message.summary), section)
+    }
+    None => (message.summary.as_str(), escape(&message.body)),
+  };
+
+  let mut blocks = vec![serde_json::json!({
+    "type": "header",
+    "text": { "type": "plain_text", "text": template::truncate(header, HEADER_LIMIT) },
+  })];
+  // an empty one is a 400 too
+  if !section.trim().is_empty() {
+    blocks.push(serde_json::json!({
+      "type": "section",
+      "text": { "type": "mrkdwn", "text": template::truncate(&section, SECTION_LIMIT) },
+    }));
+  }
+  if let Some(url) = &message.url {
+    blocks.push(serde_json::json!({
+      "type": "actions",
+      "elements": [{
+        "type": "button",
+        "text": { "type": "plain_text", "text": "Open room" },
+        "url": url,
+      }],
+    }));
+  }
+  serde_json::Value::Array(blocks)
+}
+
+/// the characters mrkdwn takes as markup
+fn escape(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::VecDeque;
+  use std::convert::Infallible;
+  use std::net::SocketAddr;
+  use std::sync::{Arc, Mutex};
+
+  use hyper::service::{make_service_fn, service_fn};
+  use hyper::{Body, Response, Server};
+
+  use super::*;
+  use crate::{Event, EventData};
+
+  /// answers with `responses` in order, then 200s, keeping the bodies posted
+  fn mock_slack(
+    responses: Vec<(u16, &'static str)>,
+  ) -> (String, Arc<Mutex<Vec<serde_json::Value>>>) {
+    let posted = Arc::new(Mutex::new(vec![]));
+    let responses = Arc::new(Mutex::new(VecDeque::from(responses)));
+    let (svc_posted, svc_responses) = (posted.clone(), responses.clone());
+    let make_svc = make_service_fn(move |_| {
+      let (posted, responses) = (svc_posted.clone(), svc_responses.clone());
+      async move {
+        Ok::<_, Infallible>(service_fn(move |req: hyper::Request<Body>| {
+          let (posted, responses) = (posted.clone(), responses.clone());
+          async move {
+            let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            posted
+              .lock()
+              .unwrap()
+              .push(serde_json::from_slice(&body).unwrap());
+            let (status, body) = responses.lock().unwrap().pop_front().unwrap_or((200, "ok"));
+            let mut res = Response::builder().status(status);
+            if status == 429 {
+              res = res.header("Retry-After", "0");
+            }
+            Ok::<_, Infallible>(res.body(Body::from(body)).unwrap())
+          }
+        }))
+      }
+    });
+    let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_svc);
+    let url = format!("http://{}/services/T0/B0/x", server.local_addr());
+    tokio::spawn(server);
+    (url, posted)
+  }
+
+  fn message(title: &str) -> Message {
+    let event = Event {
+      event_type: "StreamStarted".to_string(),
+      event_data: EventData {
+        room_id: 23058,
+        name: "3号直播间".to_string(),
+        title: title.to_string(),
+        area_name_parent: "生活".to_string(),
+        area_name_child: "影音馆".to_string(),
+        ..Default::default()
+      },
+      ..Default::default()
+    };
+    Message {
+      event_type: Some(event.event_type.clone()),
+      summary: "3号直播间 is live".to_string(),
+      body: title.to_string(),
+      url: Some("https://live.bilibili.com/23058".to_string()),
+      room_id: Some(23058),
+      event: Some(event),
+      urgent: false,
+    }
+  }
+
+  #[tokio::test]
+  async fn posts_blocks() {
+    let (url, posted) = mock_slack(vec![]);
+    SlackNotifier::new(&url)
+      .send(&message("a <b> & c"))
+      .await
+      .unwrap();
+
+    let posted = posted.lock().unwrap();
+    assert_eq!(posted.len(), 1);
+    let blocks = &posted[0]["blocks"];
+    assert_eq!(blocks[0]["text"]["text"], "3号直播间");
+    assert_eq!(
+      blocks[1]["text"]["text"],
+      "a &lt;b&gt; &amp; c\n_生活 · 影音馆_"
+    );
+    assert_eq!(
+      blocks[2]["elements"][0]["url"],
+      "https://live.bilibili.com/23058"
+    );
+    assert_eq!(
+      posted[0]["text"],
+      "*3号直播间 is live*\na &lt;b&gt; &amp; c\n<https://live.bilibili.com/23058|Open room>"
+    );
+  }
+
+  #[tokio::test]
+  async fn falls_back_to_text_without_blocks() {
+    let (url, posted) = mock_slack(vec![(400, "invalid_blocks")]);
+    SlackNotifier::new(&url)
+      .send(&message("title"))
+      .await
+      .unwrap();
+
+    let posted = posted.lock().unwrap();
+    assert_eq!(posted.len(), 2);
+    assert!(posted[0].get("blocks").is_some());
+    assert!(posted[1].get("blocks").is_none());
+    assert_eq!(posted[1]["text"], posted[0]["text"]);
+  }
+
+  #[tokio::test]
+  async fn rate_limits_are_waited_out() {
+    let (url, posted) = mock_slack(vec![(429, "rate_limited")]);
+    SlackNotifier::new(&url)
+      .send(&message("title"))
+      .await
+      .unwrap();
+    assert_eq!(posted.lock().unwrap().len(), 2);
+  }
+
+  #[tokio::test]
+  async fn other_errors_are_returned() {
+    let (url, posted) = mock_slack(vec![(404, "no_service")]);
+    let err = SlackNotifier::new(&url)
+      .send(&message("title"))
+      .await
+      .unwrap_err();
+    assert!(err.contains("no_service"), "{err}");
+    assert_eq!(posted.lock().unwrap().len(), 1);
+  }
+}
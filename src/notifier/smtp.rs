@@ -0,0 +1,453 @@
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use base64::Engine;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::oneshot;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::ServerName;
+use tokio_rustls::TlsConnector;
+
+use crate::notifier::{self, Message, Notifier, TIMEOUT};
+use crate::tls;
+
+/// the longest encoded word a header may have, RFC 2047 says
+static MAX_ENCODED_WORD: usize = 75;
+
+/// sent as the client's name, which servers hardly check
+static HELO: &str = "EHLO localhost";
+
+/// how the connection to the mail server is encrypted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpTls {
+  /// upgraded after connecting, usually on port 587
+  StartTls,
+  /// from the start, usually on port 465
+  Tls,
+}
+
+impl FromStr for SmtpTls {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "starttls" => Ok(Self::StartTls),
+      "tls" => Ok(Self::Tls),
+      _ => Err(format!("unknown tls mode `{s}`, expected starttls or tls")),
+    }
+  }
+}
+
+impl SmtpTls {
+  pub fn default_port(self) -> u16 {
+    match self {
+      Self::StartTls => 587,
+      Self::Tls => 465,
+    }
+  }
+}
+
+/// where mails go and who they're from
+#[derive(Debug)]
+pub struct SmtpOptions {
+  pub host: String,
+  pub port: u16,
+  pub tls: SmtpTls,
+  /// logged in with when both are set
+  pub user: Option<String>,
+  pub pass: Option<String>,
+  pub from: String,
+  pub to: Vec<String>,
+}
+
+/// mails notifications as plain text, those within `batch` of each other
+/// together
+pub struct SmtpNotifier {
+  mailer: Arc<Mailer>,
+  /// `None` mails each one right away
+  batch: Option<Duration>,
+  pending: Arc<Mutex<Vec<Pending>>>,
+}
+
+/// a message of the batch being gathered, with where the batch's result goes
+struct Pending {
+  message: Message,
+  mailed: oneshot::Sender<Result<(), String>>,
+}
+
+struct Mailer {
+  options: SmtpOptions,
+  connector: TlsConnector,
+}
+
+impl SmtpNotifier {
+  pub fn new(options: SmtpOptions, batch: Option<Duration>) -> Self {
+    Self {
+      mailer: Arc::new(Mailer {
+        options,
+        connector: tls::connector(),
+      }),
+      batch,
+      pending: Default::default(),
+    }
+  }
+}
+
+#[async_trait]
+impl Notifier for SmtpNotifier {
+  fn name(&self) -> &'static str {
+    "smtp"
+  }
+
+  /// batched ones wait for their batch, its result is theirs
+  async fn send(&self, message: &Message) -> Result<(), String> {
+    let Some(window) = self.batch else {
+      return self.mailer.send(std::slice::from_ref(message)).await;
+    };
+
+    let (mailed, result) = oneshot::channel();
+    let first = {
+      let mut pending = self.pending.lock().unwrap();
+      pending.push(Pending {
+        message: message.clone(),
+        mailed,
+      });
+      pending.len() == 1
+    };
+    if first {
+      let mailer = self.mailer.clone();
+      let pending = self.pending.clone();
+      tokio::spawn(async move {
+        tokio::time::sleep(window).await;
+        let batch = std::mem::take(&mut *pending.lock().unwrap());
+        mailer.send_batch(batch).await;
+      });
+    }
+    result
+      .await
+      .unwrap_or_else(|_| Err("batch dropped before it was mailed".to_string()))
+  }
+
+  /// the batch being gathered, without waiting for its window to pass
+  async fn flush(&self) {
+    let batch = std::mem::take(&mut *self.pending.lock().unwrap());
+    self.mailer.send_batch(batch).await;
+  }
+}
+
+impl Mailer {
+  /// in one mail, a flushed batch is empty when its window passes
+  async fn send_batch(&self, batch: Vec<Pending>) {
+    if batch.is_empty() {
+      return;
+    }
+    let (messages, senders): (Vec<_>, Vec<_>) =
+      batch.into_iter().map(|it| (it.message, it.mailed)).unzip();
+    let result = self.send(&messages).await;
+    for sender in senders {
+      let _ = sender.send(result.clone());
+    }
+  }
+
+  async fn send(&self, messages: &[Message]) -> Result<(), String> {
+    let mail = self.compose(messages);
+    let mail = &mail;
+    notifier::retrying(|backoff| async move {
+      let result = match tokio::time::timeout(TIMEOUT, self.deliver(mail)).await {
+        Ok(it) => it,
+        Err(_) => Err("timed out".to_string()),
+      };
+      result.map_err(|err| (err, Some(backoff)))
+    })
+    .await
+  }
+
+  async fn deliver(&self, mail: &str) -> Result<(), String> {
+    let options = &self.options;
+    let tcp = TcpStream::connect((options.host.as_str(), options.port))
+      .await
+      .map_err(|err| {
+        format!(
+          "failed to connect to {}:{}\n{err}",
+          options.host, options.port
+        )
+      })?;
+
+    let stream = match options.tls {
+      SmtpTls::Tls => self.handshake(tcp).await?,
+      SmtpTls::StartTls => {
+        let mut plain = Session::new(tcp);
+        plain.reply(220).await?;
+        plain.command(HELO, 250).await?;
+        plain.command("STARTTLS", 220).await?;
+        self.handshake(plain.into_inner()).await?
+      }
+    };
+    let mut session = Session::new(stream);
+    if options.tls == SmtpTls::Tls {
+      session.reply(220).await?;
+    }
+    // the server may offer more once it's encrypted
+    session.command(HELO, 250).await?;
+
+    if let (Some(user), Some(pass)) = (&options.user, &options.pass) {
+      let token = base64::engine::general_purpose::STANDARD.encode(format!("\0{user}\0{pass}"));
+      session
+        .command(&format!("AUTH PLAIN {token}"), 235)
+        .await
+        .map_err(|err| format!("failed to log in as {user}\n{err}"))?;
+    }
+    session
+      .command(&format!("MAIL FROM:<{}>", options.from), 250)
+      .await?;
+    for to in &options.to {
+      session.command(&format!("RCPT TO:<{to}>"), 250).await?;
+    }
+    session.command("DATA", 354).await?;
+    session.write(mail).await?;
+    session.reply(250).await?;
+    // it's sent already
+    let _ = session.command("QUIT", 221).await;
+    Ok(())
+  }
+
+  async fn handshake(&self, tcp: TcpStream) -> Result<TlsStream<TcpStream>, String> {
+    let host = &self.options.host;
+    let name =
+      ServerName::try_from(host.as_str()).map_err(|_| format!("invalid smtp host `{host}`"))?;
+    self
+      .connector
+      .connect(name, tcp)
+      .await
+      .map_err(|err| format!("tls handshake with {host} failed\n{err}"))
+  }
+
+  /// the whole mail as sent after DATA, ending with the lone `.` line
+  fn compose(&self, messages: &[Message]) -> String {
+    let subject = match messages.split_first() {
+      Some((first, [])) => first.summary.clone(),
+      Some((first, rest)) => format!("{} (+{} more)", first.summary, rest.len()),
+      None => String::new(),
+    };
+    let text = messages
+      .iter()
+      .map(describe)
+      .collect::<Vec<_>>()
+      .join("\n\n----\n\n");
+
+    let base64 = base64::engine::general_purpose::STANDARD;
+    // crlf separated lines of at most 76 chars, so nothing needs dot stuffing
+    let body = base64
+      .encode(text.replace('\n', "\r\n"))
+      .as_bytes()
+      .chunks(76)
+      .map(|it| String::from_utf8_lossy(it).into_owned())
+      .collect::<Vec<_>>()
+      .join("\r\n");
+    format!(
+      "From: {}\r\n\
+       To: {}\r\n\
+       Subject: {}\r\n\
+       Date: {}\r\n\
+       MIME-Version: 1.0\r\n\
+       Content-Type: text/plain; charset=utf-8\r\n\
+       Content-Transfer-Encoding: base64\r\n\
+       \r\n\
+       {body}\r\n\
+       .\r\n",
+      self.options.from,
+      self.options.to.join(", "),
+      encode_header(&subject),
+      chrono::Local::now().to_rfc2822(),
+    )
+  }
+}
+
+/// as encoded words of at most [`MAX_ENCODED_WORD`] chars, each on a folded
+/// line of its own and split between chars, not within one
+fn encode_header(text: &str) -> String {
+  // `=?UTF-8?B?` and `?=` around base64, which is 4 chars for every 3 bytes
+  let max_bytes = (MAX_ENCODED_WORD - 12) / 4 * 3;
+  let mut words = vec![];
+  let mut start = 0;
+  for (idx, ch) in text.char_indices() {
+    if idx + ch.len_utf8() - start > max_bytes {
+      words.push(&text[start..idx]);
+      start = idx;
+    }
+  }
+  words.push(&text[start..]);
+  let base64 = base64::engine::general_purpose::STANDARD;
+  words
+    .iter()
+    .map(|it| format!("=?UTF-8?B?{}?=", base64.encode(it)))
+    .collect::<Vec<_>>()
+    .join("\r\n ")
+}
+
+/// the notification, the room url and every field of the event
+fn describe(message: &Message) -> String {
+  let mut lines = vec![message.summary.clone(), message.body.clone()];
+  if let Some(url) = &message.url {
+    lines.push(url.clone());
+  }
+  if let Some(serde_json::Value::Object(event)) = message
+    .event
+    .as_ref()
+    .map(|it| serde_json::to_value(it).unwrap())
+  {
+    lines.push(String::new());
+    let data = event.get("EventData").and_then(|it| it.as_object());
+    let fields = event
+      .iter()
+      .filter(|(key, _)| *key != "EventData")
+      .chain(data.into_iter().flatten());
+    for (key, value) in fields {
+      match value {
+        serde_json::Value::String(value) => lines.push(format!("{key}: {value}")),
+        value => lines.push(format!("{key}: {value}")),
+      }
+    }
+  }
+  lines.join("\n")
+}
+
+/// one smtp conversation, replies are read whole, continued lines included
+struct Session<S> {
+  stream: BufReader<S>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Session<S> {
+  fn new(stream: S) -> Self {
+    Self {
+      stream: BufReader::new(stream),
+    }
+  }
+
+  fn into_inner(self) -> S {
+    self.stream.into_inner()
+  }
+
+  /// only the class of `expected` is checked, a 251 is as good as a 250
+  async fn reply(&mut self, expected: u16) -> Result<(), String> {
+    let mut text = String::new();
+    loop {
+      let mut line = String::new();
+      let read = self
+        .stream
+        .read_line(&mut line)
+        .await
+        .map_err(|err| err.to_string())?;
+      if read == 0 {
+        return Err("connection closed by the smtp server".to_string());
+      }
+      text += &line;
+      // continued lines are like `250-SIZE`, the last like `250 OK`
+      if line.as_bytes().get(3) != Some(&b'-') {
+        break;
+      }
+    }
+    let code = text
+      .get(..3)
+      .and_then(|it| u16::from_str(it).ok())
+      .ok_or_else(|| format!("invalid smtp reply `{}`", text.trim_end()))?;
+    match code / 100 == expected / 100 {
+      true => Ok(()),
+      false => Err(text.trim_end().to_string()),
+    }
+  }
+
+  async fn command(&mut self, line: &str, expected: u16) -> Result<(), String> {
+    self.write(&format!("{line}\r\n")).await?;
+    self.reply(expected).await
+  }
+
+  async fn write(&mut self, data: &str) -> Result<(), String> {
+    let stream = self.stream.get_mut();
+    stream
+      .write_all(data.as_bytes())
+      .await
+      .map_err(|err| err.to_string())?;
+    stream.flush().await.map_err(|err| err.to_string())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use base64::Engine;
+
+  use super::*;
+
+  fn notifier(batch: Option<Duration>) -> SmtpNotifier {
+    SmtpNotifier::new(
+      SmtpOptions {
+        // nothing listens there
+        host: "127.0.0.1".to_string(),
+        port: 1,
+        tls: SmtpTls::Tls,
+        user: None,
+        pass: None,
+        from: "from@example.com".to_string(),
+        to: vec!["to@example.com".to_string()],
+      },
+      batch,
+    )
+  }
+
+  fn message(summary: &str) -> Message {
+    Message {
+      event_type: None,
+      summary: summary.to_string(),
+      body: String::new(),
+      url: None,
+      room_id: None,
+      event: None,
+      urgent: false,
+    }
+  }
+
+  #[test]
+  fn long_subjects_are_split() {
+    let subject = "某某主播开播了：".repeat(10) + "abc";
+    let header = encode_header(&subject);
+    let base64 = base64::engine::general_purpose::STANDARD;
+    let mut decoded = vec![];
+    let words = header.split("\r\n ").collect::<Vec<_>>();
+    assert!(words.len() > 1);
+    for word in words {
+      assert!(word.len() <= MAX_ENCODED_WORD, "{word}");
+      let encoded = word
+        .strip_prefix("=?UTF-8?B?")
+        .and_then(|it| it.strip_suffix("?="))
+        .unwrap();
+      let bytes = base64.decode(encoded).unwrap();
+      // each word is whole chars
+      assert!(std::str::from_utf8(&bytes).is_ok());
+      decoded.extend(bytes);
+    }
+    assert_eq!(String::from_utf8(decoded).unwrap(), subject);
+  }
+
+  #[tokio::test]
+  async fn batched_failures_are_returned() {
+    let smtp = notifier(Some(Duration::from_millis(10)));
+    let (a, b) = (message("a"), message("b"));
+    let (first, second) = tokio::join!(smtp.send(&a), smtp.send(&b));
+    assert!(first.is_err());
+    assert_eq!(first, second);
+  }
+
+  #[tokio::test]
+  async fn flushed_batches_are_sent_at_once() {
+    let smtp = notifier(Some(Duration::from_secs(3600)));
+    let a = message("a");
+    let sent = smtp.send(&a);
+    tokio::pin!(sent);
+    assert!(futures_util::poll!(&mut sent).is_pending());
+    smtp.flush().await;
+    assert!(sent.await.is_err());
+  }
+}
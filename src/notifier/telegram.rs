@@ -0,0 +1,164 @@
+use async_trait::async_trait;
+
+use crate::notifier::{self, Message, Notifier, TIMEOUT};
+
+/// of one message, in chars
+static LIMIT: usize = 4096;
+
+/// sends to a chat through a telegram bot
+pub struct TelegramNotifier {
+  client: reqwest::Client,
+  url: String,
+  chat_id: String,
+}
+
+impl TelegramNotifier {
+  /// `chat_id` is a number or the `@username` of a channel
+  pub fn new(token: &str, chat_id: &str) -> Self {
+    Self {
+      client: reqwest::Client::new(),
+      url: format!("https://api.telegram.org/bot{token}/sendMessage"),
+      chat_id: chat_id.to_string(),
+    }
+  }
+}
+
+#[async_trait]
+impl Notifier for TelegramNotifier {
+  fn name(&self) -> &'static str {
+    "telegram"
+  }
+
+  /// longer ones go as several messages, in order, a 429 is waited out as
+  /// telegram asks
+  async fn send(&self, message: &Message) -> Result<(), String> {
+    for text in split(&text(message), LIMIT) {
+      let req = self
+        .client
+        .post(&self.url)
+        .timeout(TIMEOUT)
+        .json(&serde_json::json!({
+          "chat_id": self.chat_id,
+          "text": text,
+          "parse_mode": "MarkdownV2",
+        }));
+      notifier::send_retrying(req).await?;
+    }
+    Ok(())
+  }
+}
+
+/// cut into chunks of at most `limit` chars at line ends, lines longer than
+/// that are cut anywhere but right after an escaping backslash
+fn split(text: &str, limit: usize) -> Vec<String> {
+  let mut chunks = vec![];
+  let mut chunk = String::new();
+  let mut len = 0;
+  for line in text.split_inclusive('\n') {
+    let mut line = line;
+    let mut line_len = line.chars().count();
+    if len + line_len > limit && len > 0 {
+      chunks.push(std::mem::take(&mut chunk));
+      len = 0;
+    }
+    while line_len > limit {
+      let mut end = line
+        .char_indices()
+        .nth(limit)
+        .map_or(line.len(), |(i, _)| i);
+      let backslashes = line[..end]
+        .chars()
+        .rev()
+        .take_while(|it| *it == '\\')
+        .count();
+      if backslashes % 2 == 1 {
+        end -= 1;
+      }
+      chunks.push(line[..end].to_string());
+      line = &line[end..];
+      line_len = line.chars().count();
+    }
+    chunk.push_str(line);
+    len += line_len;
+  }
+  chunks.push(chunk);
+
+  chunks
+    .into_iter()
+    .map(|it| it.trim_end_matches('\n').to_string())
+    .filter(|it| !it.is_empty())
+    .collect()
+}
+
+/// the summary in bold, then the body and a link to the room
+fn text(message: &Message) -> String {
+  let mut text = format!("*{}*", escape(&message.summary));
+  if !message.body.is_empty() {
+    text += &format!("\n{}", escape(&message.body));
+  }
+  if let (Some(url), Some(room_id)) = (&message.url, message.room_id) {
+    let url = url.replace('\\', "\\\\").replace(')', "\\)");
+    text += &format!("\n[live\\.bilibili\\.com/{room_id}]({url})");
+  }
+  text
+}
+
+/// everything MarkdownV2 takes as formatting is backslash escaped
+fn escape(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    if "_*[]()~`>#+-=|{}.!\\".contains(c) {
+      escaped.push('\\');
+    }
+    escaped.push(c);
+  }
+  escaped
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn lens(chunks: &[String]) -> Vec<usize> {
+    chunks.iter().map(|it| it.chars().count()).collect()
+  }
+
+  #[test]
+  fn long_text_is_split_into_chunks() {
+    assert_eq!(lens(&split(&"a".repeat(10000), LIMIT)), [4096, 4096, 1808]);
+    assert_eq!(lens(&split(&"测".repeat(5000), LIMIT)), [4096, 904]);
+    assert_eq!(lens(&split(&"a".repeat(LIMIT), LIMIT)), [LIMIT]);
+
+    // at line ends when the lines fit
+    let line = "b".repeat(2000);
+    let chunks = split(&[line.as_str(); 3].join("\n"), LIMIT);
+    assert_eq!(chunks, [format!("{line}\n{line}"), line.clone()]);
+  }
+
+  #[test]
+  fn escapes_arent_split() {
+    let text = format!("{}\\.", "a".repeat(LIMIT - 1));
+    let chunks = split(&text, LIMIT);
+    assert_eq!(chunks, ["a".repeat(LIMIT - 1), "\\.".to_string()]);
+  }
+
+  #[test]
+  fn markdown_is_escaped() {
+    assert_eq!(escape("a_b*c.d!"), "a\\_b\\*c\\.d\\!");
+    assert_eq!(escape("[x](y) \\ 测试"), "\\[x\\]\\(y\\) \\\\ 测试");
+
+    let message = Message {
+      event_type: None,
+      summary: "3号直播间 (live)".to_string(),
+      body: "1+1=2".to_string(),
+      url: Some("https://live.bilibili.com/23058".to_string()),
+      room_id: Some(23058),
+      event: None,
+      urgent: false,
+    };
+    assert_eq!(
+      text(&message),
+      "*3号直播间 \\(live\\)*\n1\\+1\\=2\n[live\\.bilibili\\.com/23058](https://live.bilibili.com/23058)"
+    );
+  }
+}
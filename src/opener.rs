@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tracing::{error, info};
+
+use crate::filter::RoomFilter;
+
+pub fn room_url(room_id: i64) -> String {
+  format!("https://live.bilibili.com/{room_id}")
+}
+
+/// open `url` with `opener`, split on whitespace with the url appended, or
+/// the platform opener, blocking until the opener exits
+pub fn open(url: &str, opener: Option<&str>) -> std::io::Result<()> {
+  let mut command = match opener {
+    Some(opener) => {
+      let mut parts = opener.split_whitespace();
+      let mut command = Command::new(parts.next().unwrap_or_default());
+      command.args(parts);
+      command
+    }
+    None => platform_opener(),
+  };
+
+  let status = command
+    .arg(url)
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .status()?;
+  if !status.success() {
+    return Err(std::io::Error::other(format!(
+      "opener exited with {status}"
+    )));
+  }
+  Ok(())
+}
+
+fn platform_opener() -> Command {
+  #[cfg(target_os = "macos")]
+  let command = Command::new("open");
+
+  #[cfg(all(unix, not(target_os = "macos")))]
+  let command = Command::new("xdg-open");
+
+  #[cfg(target_os = "windows")]
+  let command = {
+    let mut command = Command::new("cmd");
+    command.args(["/c", "start", ""]);
+    command
+  };
+
+  command
+}
+
+/// opens rooms in the browser when they start streaming
+pub struct AutoOpen {
+  /// all rooms when `None`
+  pub rooms: Option<RoomFilter>,
+  pub opener: Option<String>,
+  /// a room isn't opened again within this long
+  pub cooldown: Duration,
+  pub last_opened: Mutex<HashMap<i64, Instant>>,
+}
+
+impl AutoOpen {
+  /// open the room in the background unless it was opened recently
+  pub fn trigger(&self, room_id: i64) {
+    if let Some(rooms) = &self.rooms {
+      if !rooms.contains(room_id) {
+        return;
+      }
+    }
+
+    {
+      let mut last_opened = self.last_opened.lock().unwrap();
+      let now = Instant::now();
+      last_opened.retain(|_, it| now.duration_since(*it) < self.cooldown);
+      if last_opened.contains_key(&room_id) {
+        info!("{room_id} opened recently, not opening again");
+        return;
+      }
+      last_opened.insert(room_id, now);
+    }
+
+    let url = room_url(room_id);
+    let opener = self.opener.clone();
+    tokio::task::spawn_blocking(move || match open(&url, opener.as_deref()) {
+      Ok(()) => info!("opened {url}"),
+      Err(err) => error!("failed to open {url}\n{err:#?}"),
+    });
+  }
+}
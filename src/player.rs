@@ -0,0 +1,93 @@
+use std::process::Stdio;
+
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use tracing::{error, info};
+
+use crate::filter::RoomFilter;
+use crate::hook;
+use crate::template::Template;
+use crate::Event;
+
+/// a command run when a stream starts, like `mpv https://live.bilibili.com/{room_id}`
+pub struct Player {
+  /// the program and its arguments, each rendered on its own so titles with
+  /// spaces stay one argument and nothing goes through a shell
+  args: Vec<Template>,
+  /// all rooms when `None`
+  rooms: Option<RoomFilter>,
+  log_output: bool,
+}
+
+impl Player {
+  pub fn parse(command: &str, rooms: Option<RoomFilter>, log_output: bool) -> Result<Self, String> {
+    let args = hook::split(command)?
+      .iter()
+      .map(|it| Template::parse(it, Some("StreamStarted")))
+      .collect::<Result<Vec<_>, _>>()?;
+    if args.is_empty() {
+      return Err("empty command".to_string());
+    }
+    Ok(Self {
+      args,
+      rooms,
+      log_output,
+    })
+  }
+
+  /// start the command for the event in the background, it's waited on so
+  /// it doesn't linger as a zombie
+  pub fn spawn(&self, event: &Event) {
+    if let Some(rooms) = &self.rooms {
+      if !rooms.contains(event.event_data.room_id) {
+        return;
+      }
+    }
+
+    let args = self
+      .args
+      .iter()
+      .map(|it| it.render(event))
+      .collect::<Vec<_>>();
+    let output = || match self.log_output {
+      true => Stdio::piped(),
+      false => Stdio::null(),
+    };
+    let child = Command::new(&args[0])
+      .args(&args[1..])
+      .stdin(Stdio::null())
+      .stdout(output())
+      .stderr(output())
+      .spawn();
+    let mut child = match child {
+      Ok(it) => it,
+      Err(err) => {
+        error!("failed to run {}\n{err:#?}", args[0]);
+        return;
+      }
+    };
+    info!("started {}", args[0]);
+
+    let program = args[0].clone();
+    if let Some(stdout) = child.stdout.take() {
+      tokio::spawn(log_lines(program.clone(), stdout));
+    }
+    if let Some(stderr) = child.stderr.take() {
+      tokio::spawn(log_lines(program.clone(), stderr));
+    }
+    tokio::spawn(async move {
+      match child.wait().await {
+        Ok(status) if status.success() => info!("{program} exited"),
+        Ok(status) => error!("{program} exited with {status}"),
+        Err(err) => error!("failed to wait for {program}\n{err:#?}"),
+      }
+    });
+  }
+}
+
+async fn log_lines(program: String, output: impl AsyncRead + Unpin) {
+  let mut lines = BufReader::new(output).lines();
+  while let Ok(Some(line)) = lines.next_line().await {
+    info!("{program}: {line}");
+  }
+}
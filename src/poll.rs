@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use chrono::Local;
+use serde::Deserialize;
+
+use crate::{Event, EventData};
+
+/// takes many rooms at once, by their long or short id
+static ROOMS_API: &str = "https://api.live.bilibili.com/xlive/web-room/v1/index/getRoomBaseInfo";
+
+/// rooms asked for per request
+static BATCH: usize = 20;
+
+/// between the requests of one poll, to be polite
+static BATCH_DELAY: Duration = Duration::from_secs(1);
+
+static TIMEOUT: Duration = Duration::from_secs(10);
+
+/// polls happen at most this often
+pub static MIN_INTERVAL: Duration = Duration::from_secs(10);
+
+/// how long the wait between polls grows to while the api fails
+pub static MAX_BACKOFF: Duration = Duration::from_secs(600);
+
+/// of the event ids of polled events
+static EVENT_ID_PREFIX: &str = "poll-";
+
+/// whether each room is live as of its last StreamStarted or StreamEnded, from
+/// the recorder or a poll, so a start both report is only notified once
+#[derive(Default)]
+pub struct LiveRooms {
+  live: Mutex<HashMap<i64, bool>>,
+}
+
+impl LiveRooms {
+  /// whether the event starts or ends a stream that wasn't already, other
+  /// events always do
+  pub fn changes(&self, event: &Event) -> bool {
+    let live = match event.event_type.as_str() {
+      "StreamStarted" => true,
+      "StreamEnded" => false,
+      _ => return true,
+    };
+    let previous = self
+      .live
+      .lock()
+      .unwrap()
+      .insert(event.event_data.room_id, live);
+    previous != Some(live)
+  }
+
+  pub fn is_live(&self, room_id: i64) -> Option<bool> {
+    self.live.lock().unwrap().get(&room_id).copied()
+  }
+
+  /// for rooms nothing's known of yet, without an event
+  pub fn seed(&self, room_id: i64, live: bool) {
+    self.live.lock().unwrap().entry(room_id).or_insert(live);
+  }
+}
+
+/// polled events know nothing of recording and danmaku
+pub fn synthesized(event: &Event) -> bool {
+  event.event_id.starts_with(EVENT_ID_PREFIX)
+}
+
+/// asks the live api how `rooms` are doing
+pub struct Poller {
+  client: reqwest::Client,
+  rooms: Vec<i64>,
+}
+
+#[derive(Deserialize)]
+struct ApiResponse<T> {
+  code: i64,
+  #[serde(default)]
+  message: String,
+  data: Option<T>,
+}
+
+#[derive(Deserialize)]
+struct BaseInfos {
+  #[serde(default)]
+  by_room_ids: HashMap<String, BaseInfo>,
+}
+
+#[derive(Deserialize)]
+struct BaseInfo {
+  room_id: i64,
+  #[serde(default)]
+  short_id: i64,
+  #[serde(default)]
+  uname: String,
+  #[serde(default)]
+  title: String,
+  #[serde(default)]
+  parent_area_name: String,
+  #[serde(default)]
+  area_name: String,
+  /// 1 is live, 2 is a rerun, which isn't
+  live_status: i64,
+}
+
+impl Poller {
+  pub fn new(rooms: Vec<i64>) -> Self {
+    Self {
+      client: reqwest::Client::new(),
+      rooms,
+    }
+  }
+
+  /// a StreamStarted for each room that's live and a StreamEnded for the
+  /// others, as the recorder would send them
+  pub async fn poll(&self) -> Result<Vec<Event>, String> {
+    let mut events = vec![];
+    for (i, rooms) in self.rooms.chunks(BATCH).enumerate() {
+      if i > 0 {
+        tokio::time::sleep(BATCH_DELAY).await;
+      }
+      let query = rooms
+        .iter()
+        .map(|it| ("room_ids", it.to_string()))
+        .chain([("req_biz", "web_room_componet".to_string())])
+        .collect::<Vec<_>>();
+      let res = self
+        .client
+        .get(ROOMS_API)
+        .query(&query)
+        .timeout(TIMEOUT)
+        .send()
+        .await
+        .and_then(|it| it.error_for_status())
+        .map_err(|err| err.to_string())?
+        .json::<ApiResponse<BaseInfos>>()
+        .await
+        .map_err(|err| err.to_string())?;
+      let infos = match res.data {
+        Some(data) if res.code == 0 => data.by_room_ids,
+        _ => return Err(format!("{}: {}", res.code, res.message)),
+      };
+      events.extend(infos.into_values().map(event));
+    }
+    Ok(events)
+  }
+}
+
+fn event(info: BaseInfo) -> Event {
+  let live = info.live_status == 1;
+  let now = Local::now();
+  Event {
+    event_type: match live {
+      true => "StreamStarted",
+      false => "StreamEnded",
+    }
+    .to_string(),
+    event_timestamp: now.to_rfc3339(),
+    event_id: format!(
+      "{EVENT_ID_PREFIX}{}-{}",
+      info.room_id,
+      now.timestamp_millis()
+    ),
+    event_data: EventData {
+      room_id: info.room_id,
+      short_id: info.short_id,
+      name: info.uname,
+      title: info.title,
+      area_name_parent: info.parent_area_name,
+      area_name_child: info.area_name,
+      streaming: live,
+      ..Default::default()
+    },
+  }
+}
+
+/// `wait` give or take a tenth, so instances started together don't poll
+/// together
+pub fn jittered(wait: Duration) -> Duration {
+  let nanos = SystemTime::now()
+    .duration_since(SystemTime::UNIX_EPOCH)
+    .unwrap_or_default()
+    .subsec_nanos();
+  // from 0.9 to 1.1
+  wait.mul_f64(0.9 + f64::from(nanos % 1000) / 5000.0)
+}
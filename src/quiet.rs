@@ -0,0 +1,111 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{Local, NaiveTime};
+
+use crate::notifier::Message;
+
+/// a daily local time window like `23:00-08:00`, which may wrap midnight,
+/// the start is in it and the end isn't
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct QuietHours {
+  start: NaiveTime,
+  end: NaiveTime,
+}
+
+impl FromStr for QuietHours {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let parse_time = |it: &str| {
+      NaiveTime::parse_from_str(it.trim(), "%H:%M")
+        .map_err(|_| format!("invalid time `{}`, expected HH:MM", it.trim()))
+    };
+    let (start, end) = s
+      .split_once('-')
+      .ok_or_else(|| format!("invalid quiet hours `{s}`, expected HH:MM-HH:MM"))?;
+    let (start, end) = (parse_time(start)?, parse_time(end)?);
+    if start == end {
+      return Err(format!("empty quiet hours `{s}`"));
+    }
+    Ok(Self { start, end })
+  }
+}
+
+impl fmt::Debug for QuietHours {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{}-{}",
+      self.start.format("%H:%M"),
+      self.end.format("%H:%M")
+    )
+  }
+}
+
+impl QuietHours {
+  pub fn contains(&self, time: NaiveTime) -> bool {
+    match self.start < self.end {
+      true => self.start <= time && time < self.end,
+      false => self.start <= time || time < self.end,
+    }
+  }
+}
+
+/// what happens to notifications during quiet hours
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuietMode {
+  /// sent as one digest once they're over
+  Defer,
+  /// only logged
+  Drop,
+}
+
+impl FromStr for QuietMode {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "defer" => Ok(Self::Defer),
+      "drop" => Ok(Self::Drop),
+      _ => Err(format!("unknown quiet mode `{s}`, expected defer or drop")),
+    }
+  }
+}
+
+pub struct Quiet {
+  /// none with only the tray's mute
+  pub hours: Option<QuietHours>,
+  pub mode: QuietMode,
+  /// till when the tray muted notifications, taken as quiet hours
+  pub muted_until: Mutex<Option<Instant>>,
+  /// kept in memory only, lost on restart
+  pub deferred: Mutex<Vec<Message>>,
+}
+
+impl Quiet {
+  /// within quiet hours or muted
+  pub fn active(&self) -> bool {
+    self.muted().is_some()
+      || self
+        .hours
+        .is_some_and(|it| it.contains(Local::now().time()))
+  }
+
+  /// till when it's muted, if it is
+  pub fn muted(&self) -> Option<Instant> {
+    let until = *self.muted_until.lock().unwrap();
+    until.filter(|it| Instant::now() < *it)
+  }
+
+  /// mutes for `duration` from now, or unmutes when none
+  pub fn mute(&self, duration: Option<Duration>) {
+    *self.muted_until.lock().unwrap() = duration.map(|it| Instant::now() + it);
+  }
+
+  pub fn take_deferred(&self) -> Vec<Message> {
+    std::mem::take(&mut *self.deferred.lock().unwrap())
+  }
+}
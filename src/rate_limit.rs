@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// at most `max` notifications in any `window`, like `10/10m`
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Limit {
+  pub max: usize,
+  pub window: Duration,
+}
+
+impl FromStr for Limit {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let invalid =
+      || format!("invalid limit `{s}`, expected <count>/<seconds>, like 10/600 or 10/10m");
+    let (max, window) = s.split_once('/').ok_or_else(invalid)?;
+    let max = usize::from_str(max.trim()).map_err(|_| invalid())?;
+    let window = window.trim();
+    let (amount, unit) = match window.char_indices().last() {
+      Some((idx, 'h')) => (&window[..idx], 3600),
+      Some((idx, 'm')) => (&window[..idx], 60),
+      Some((idx, 's')) => (&window[..idx], 1),
+      _ => (window, 1),
+    };
+    let amount = u64::from_str(amount).map_err(|_| invalid())?;
+    if max == 0 || amount == 0 {
+      return Err(invalid());
+    }
+    Ok(Self {
+      max,
+      window: Duration::from_secs(amount * unit),
+    })
+  }
+}
+
+impl fmt::Debug for Limit {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}/{}s", self.max, self.window.as_secs())
+  }
+}
+
+/// a sliding window over the notifications sent
+pub struct RateLimit {
+  pub limit: Limit,
+  pub state: Mutex<Window>,
+}
+
+#[derive(Default)]
+pub struct Window {
+  /// oldest first
+  sent: VecDeque<Instant>,
+  /// since the limit was last hit
+  suppressed: u64,
+}
+
+impl RateLimit {
+  pub fn new(limit: Limit) -> Self {
+    Self {
+      limit,
+      state: Default::default(),
+    }
+  }
+
+  /// whether another notification may be sent now, counted if so
+  pub fn try_acquire(&self) -> bool {
+    self.acquire_at(Instant::now())
+  }
+
+  fn acquire_at(&self, now: Instant) -> bool {
+    let mut state = self.state.lock().unwrap();
+    self.prune(&mut state, now);
+    if state.sent.len() >= self.limit.max {
+      state.suppressed += 1;
+      return false;
+    }
+    state.sent.push_back(now);
+    true
+  }
+
+  /// once there's room again, how many were suppressed while there wasn't
+  pub fn take_suppressed(&self) -> Option<u64> {
+    self.suppressed_at(Instant::now())
+  }
+
+  fn suppressed_at(&self, now: Instant) -> Option<u64> {
+    let mut state = self.state.lock().unwrap();
+    self.prune(&mut state, now);
+    if state.suppressed == 0 || state.sent.len() >= self.limit.max {
+      return None;
+    }
+    Some(std::mem::take(&mut state.suppressed))
+  }
+
+  fn prune(&self, state: &mut Window, now: Instant) {
+    while let Some(sent) = state.sent.front() {
+      if now.duration_since(*sent) < self.limit.window {
+        break;
+      }
+      state.sent.pop_front();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn limit(max: usize, secs: u64) -> RateLimit {
+    RateLimit::new(Limit {
+      max,
+      window: Duration::from_secs(secs),
+    })
+  }
+
+  #[test]
+  fn parses_limits() {
+    let limit = Limit::from_str("10/10m").unwrap();
+    assert_eq!(limit.max, 10);
+    assert_eq!(limit.window, Duration::from_secs(600));
+    assert_eq!(
+      Limit::from_str(" 3 / 2h").unwrap().window,
+      Duration::from_secs(7200)
+    );
+    assert_eq!(
+      Limit::from_str("5/30").unwrap().window,
+      Duration::from_secs(30)
+    );
+    for invalid in ["0/10m", "10/0", "10", "10/m", "x/10m", "10/10d"] {
+      assert!(Limit::from_str(invalid).is_err(), "{invalid}");
+    }
+  }
+
+  #[test]
+  fn window_slides_at_its_boundary() {
+    let limit = limit(2, 10);
+    let start = Instant::now();
+    let window = Duration::from_secs(10);
+    assert!(limit.acquire_at(start));
+    assert!(limit.acquire_at(start + Duration::from_secs(5)));
+    assert!(!limit.acquire_at(start + Duration::from_secs(9)));
+
+    // the first one is still in the window just before it ends
+    let just_before = start + window - Duration::from_millis(1);
+    assert!(!limit.acquire_at(just_before));
+    // and out of it once it does
+    assert!(limit.acquire_at(start + window));
+    assert!(!limit.acquire_at(start + window));
+  }
+
+  #[test]
+  fn suppressed_are_reported_once_there_is_room() {
+    let limit = limit(1, 10);
+    let start = Instant::now();
+    assert!(limit.acquire_at(start));
+    assert!(!limit.acquire_at(start + Duration::from_secs(1)));
+    assert!(!limit.acquire_at(start + Duration::from_secs(2)));
+
+    let window = Duration::from_secs(10);
+    assert_eq!(
+      limit.suppressed_at(start + window - Duration::from_millis(1)),
+      None
+    );
+    assert_eq!(limit.suppressed_at(start + window), Some(2));
+    assert_eq!(limit.suppressed_at(start + window), None);
+  }
+}
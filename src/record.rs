@@ -0,0 +1,182 @@
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use chrono::{DateTime, Local};
+use serde_json::{Map, Value};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+/// records waiting to be written, past this they're dropped
+static BACKLOG: usize = 1000;
+
+/// when the events file is moved aside for a new one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotate {
+  /// on the first event of a new day
+  Daily,
+  /// once it's past this many bytes
+  Size(u64),
+}
+
+impl FromStr for Rotate {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let err = || format!("invalid rotation `{s}`, expected daily or a size like 10M");
+    if s == "daily" {
+      return Ok(Self::Daily);
+    }
+    let (digits, unit) = match s.char_indices().find(|(_, it)| !it.is_ascii_digit()) {
+      Some((i, _)) => s.split_at(i),
+      None => (s, ""),
+    };
+    let unit = match unit.to_ascii_uppercase().trim_end_matches('B') {
+      "" => 1,
+      "K" => 1 << 10,
+      "M" => 1 << 20,
+      "G" => 1 << 30,
+      _ => return Err(err()),
+    };
+    let size = u64::from_str(digits).map_err(|_| err())?;
+    match size {
+      0 => Err(err()),
+      size => Ok(Self::Size(size * unit)),
+    }
+  }
+}
+
+/// appends every received event to a file as one json line, in the format the
+/// `replay` subcommand reads, with `ReceivedAt`, `Source` and `Decision` added
+pub struct EventRecorder {
+  sender: mpsc::Sender<String>,
+}
+
+impl EventRecorder {
+  /// opens the file right away so a bad path shows at startup
+  pub async fn start(path: PathBuf, rotate: Rotate) -> Result<Self, String> {
+    let file = Output::open(&path).await?;
+    let (sender, receiver) = mpsc::channel(BACKLOG);
+    tokio::spawn(write(path, rotate, file, receiver));
+    Ok(Self { sender })
+  }
+
+  /// `body` is kept as `Raw` when it isn't a json object
+  pub fn record(&self, body: &[u8], addr: SocketAddr, path: &str, decision: &str) {
+    let mut line = match serde_json::from_slice::<Value>(body) {
+      Ok(Value::Object(it)) => it,
+      _ => {
+        let mut it = Map::new();
+        let raw = String::from_utf8_lossy(body).into_owned();
+        it.insert("Raw".to_string(), Value::String(raw));
+        it
+      }
+    };
+    line.insert(
+      "ReceivedAt".to_string(),
+      Value::String(Local::now().to_rfc3339()),
+    );
+    line.insert(
+      "Source".to_string(),
+      serde_json::json!({ "Addr": addr.to_string(), "Path": path }),
+    );
+    line.insert("Decision".to_string(), Value::String(decision.to_string()));
+
+    if self
+      .sender
+      .try_send(Value::Object(line).to_string())
+      .is_err()
+    {
+      warn!("event recording can't keep up, dropped one");
+    }
+  }
+}
+
+struct Output {
+  file: File,
+  len: u64,
+  opened: DateTime<Local>,
+}
+
+impl Output {
+  async fn open(path: &Path) -> Result<Self, String> {
+    let file = OpenOptions::new()
+      .create(true)
+      .append(true)
+      .open(path)
+      .await
+      .map_err(|err| format!("failed to open {}\n{err:#?}", path.display()))?;
+    let len = file.metadata().await.map_or(0, |it| it.len());
+    Ok(Self {
+      file,
+      len,
+      opened: Local::now(),
+    })
+  }
+
+  fn due(&self, rotate: Rotate) -> bool {
+    match rotate {
+      Rotate::Daily => self.opened.date_naive() != Local::now().date_naive(),
+      Rotate::Size(size) => self.len >= size,
+    }
+  }
+}
+
+async fn write(path: PathBuf, rotate: Rotate, file: Output, mut receiver: mpsc::Receiver<String>) {
+  let mut output = Some(file);
+  while let Some(line) = receiver.recv().await {
+    if output.as_ref().is_some_and(|it| it.due(rotate)) {
+      output = None;
+      rotate_away(&path).await;
+    }
+    if output.is_none() {
+      match Output::open(&path).await {
+        Ok(it) => output = Some(it),
+        Err(err) => {
+          warn!("{err}");
+          continue;
+        }
+      }
+    }
+
+    let Some(file) = &mut output else {
+      continue;
+    };
+    let line = format!("{line}\n");
+    // tokio only hands writes to a blocking thread, the flush waits for it
+    let written = match file.file.write_all(line.as_bytes()).await {
+      Ok(()) => file.file.flush().await,
+      Err(err) => Err(err),
+    };
+    match written {
+      Ok(()) => file.len += line.len() as u64,
+      Err(err) => {
+        warn!("failed to record event\n{err:#?}");
+        output = None;
+      }
+    }
+  }
+}
+
+/// moved to a name with the current time, like `events.2006-01-02-150405.jsonl`
+async fn rotate_away(path: &Path) {
+  let time = Local::now().format("%Y-%m-%d-%H%M%S");
+  let name = match (path.file_stem(), path.extension()) {
+    (Some(stem), Some(ext)) => format!(
+      "{}.{time}.{}",
+      stem.to_string_lossy(),
+      ext.to_string_lossy()
+    ),
+    _ => format!(
+      "{}.{time}",
+      path.file_name().unwrap_or_default().to_string_lossy()
+    ),
+  };
+  let rotated = path.with_file_name(name);
+  match tokio::fs::rename(path, &rotated).await {
+    Ok(()) => info!("recorded events moved to {}", rotated.display()),
+    Err(err) => warn!("failed to rotate {}\n{err:#?}", path.display()),
+  }
+}
@@ -0,0 +1,370 @@
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use base64::Engine;
+use chrono::{DateTime, Local, TimeZone};
+use hyper::body::Bytes;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+
+/// for each attempt
+static TIMEOUT: Duration = Duration::from_secs(10);
+
+/// a target that keeps failing is tried this often at most
+static MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// a webhook as the recorder sent it
+#[derive(Debug, Clone)]
+pub struct Payload {
+  pub content_type: Option<String>,
+  /// like `gzip`, the body isn't decompressed
+  pub content_encoding: Option<String>,
+  pub body: Bytes,
+  /// given up on once it's older than `--relay-max-age-secs`
+  pub received: DateTime<Local>,
+}
+
+/// a line of a spool file
+#[derive(Serialize, Deserialize)]
+struct Spooled {
+  content_type: Option<String>,
+  content_encoding: Option<String>,
+  /// base64, it may be gzipped
+  body: String,
+  /// unix seconds, taken as now for spool files written without it
+  #[serde(default)]
+  received: Option<i64>,
+}
+
+/// where webhooks are passed on unchanged, `--relay-url`
+pub struct RelayTarget {
+  client: reqwest::Client,
+  url: String,
+  /// logged instead of the url, which may carry a secret
+  host: String,
+  capacity: usize,
+  max_age: Duration,
+  /// payloads not delivered yet, the first is the one being tried, with an id
+  /// to tell whether it was dropped meanwhile
+  spool: Mutex<Spool>,
+  /// kept like the spool, when `--relay-spool-dir` is set
+  file: Option<PathBuf>,
+  wake: Notify,
+  /// for the task writing `file`, when the spool changed
+  changed: Notify,
+  status: Mutex<Status>,
+}
+
+#[derive(Default)]
+struct Spool {
+  payloads: VecDeque<(u64, Payload)>,
+  next_id: u64,
+}
+
+#[derive(Default)]
+struct Status {
+  last_success: Option<DateTime<Local>>,
+  consecutive_failures: u32,
+  last_error: Option<String>,
+  dropped: u64,
+}
+
+/// a target for each url, each delivering on a task of its own, with what a
+/// previous run left in `spool_dir` queued first
+pub fn start(
+  urls: &[String],
+  capacity: usize,
+  max_age: Duration,
+  spool_dir: Option<&Path>,
+) -> Result<Vec<Arc<RelayTarget>>, String> {
+  if let Some(dir) = spool_dir {
+    std::fs::create_dir_all(dir)
+      .map_err(|err| format!("failed to create {}\n{err}", dir.display()))?;
+  }
+
+  let mut targets = vec![];
+  for url in urls {
+    let host = reqwest::Url::parse(url)
+      .map_err(|err| format!("invalid --relay-url `{url}`: {err}"))?
+      .host_str()
+      .unwrap_or_default()
+      .to_string();
+    // urls on one host only differ by the hash
+    let digest = ring::digest::digest(&ring::digest::SHA256, url.as_bytes());
+    let file = spool_dir.map(|it| {
+      it.join(format!(
+        "{host}-{}.jsonl",
+        hex::encode(&digest.as_ref()[..8])
+      ))
+    });
+
+    let mut spool = Spool::default();
+    if let Some(file) = &file {
+      for payload in load(file).into_iter().rev().take(capacity).rev() {
+        spool.push(payload);
+      }
+      if !spool.payloads.is_empty() {
+        info!("{} spooled webhooks for {host}", spool.payloads.len());
+      }
+    }
+
+    let target = Arc::new(RelayTarget {
+      client: reqwest::Client::new(),
+      url: url.clone(),
+      host,
+      capacity,
+      max_age,
+      spool: Mutex::new(spool),
+      file,
+      wake: Notify::new(),
+      changed: Notify::new(),
+      status: Default::default(),
+    });
+    tokio::spawn(target.clone().run());
+    if target.file.is_some() {
+      tokio::spawn(target.clone().write_spool());
+    }
+    targets.push(target);
+  }
+  Ok(targets)
+}
+
+impl Spool {
+  fn push(&mut self, payload: Payload) {
+    self.payloads.push_back((self.next_id, payload));
+    self.next_id += 1;
+  }
+}
+
+impl RelayTarget {
+  /// queued behind those not delivered yet, the oldest is dropped when the
+  /// spool is full
+  pub fn relay(&self, payload: Payload) {
+    let mut spool = self.spool.lock().unwrap();
+    spool.push(payload);
+    if spool.payloads.len() > self.capacity {
+      spool.payloads.pop_front();
+      self.status.lock().unwrap().dropped += 1;
+      warn!(
+        "relay spool for {} full, dropped the oldest webhook",
+        self.host
+      );
+    }
+    self.save();
+    drop(spool);
+    self.wake.notify_one();
+  }
+
+  /// for /healthz
+  pub fn status(&self) -> serde_json::Value {
+    let status = self.status.lock().unwrap();
+    serde_json::json!({
+      "host": self.host,
+      "pending": self.spool.lock().unwrap().payloads.len(),
+      "last_success": status.last_success.map(|it| it.to_rfc3339()),
+      "consecutive_failures": status.consecutive_failures,
+      "last_error": status.last_error,
+      "dropped": status.dropped,
+    })
+  }
+
+  async fn run(self: Arc<Self>) {
+    let mut backoff = Duration::from_secs(1);
+    loop {
+      let next = self.spool.lock().unwrap().payloads.front().cloned();
+      let Some((id, payload)) = next else {
+        self.wake.notified().await;
+        continue;
+      };
+
+      let age = (Local::now() - payload.received)
+        .to_std()
+        .unwrap_or_default();
+      let result = match age > self.max_age {
+        true => Err((format!("not relayed within {:?}", self.max_age), false)),
+        false => self.post(&payload).await,
+      };
+      let failed = match result {
+        Ok(()) => {
+          let mut status = self.status.lock().unwrap();
+          status.last_success = Some(Local::now());
+          status.consecutive_failures = 0;
+          false
+        }
+        // the target won't take it however often it's sent, or it's too old to
+        Err((err, false)) => {
+          error!("relayed webhook for {} dropped\n{err}", self.host);
+          let mut status = self.status.lock().unwrap();
+          status.dropped += 1;
+          status.last_error = Some(err);
+          false
+        }
+        Err((err, true)) => {
+          warn!(
+            "failed to relay webhook to {}, trying again in {backoff:?}\n{err}",
+            self.host
+          );
+          let mut status = self.status.lock().unwrap();
+          status.consecutive_failures += 1;
+          status.last_error = Some(err);
+          true
+        }
+      };
+
+      if failed {
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+        continue;
+      }
+      backoff = Duration::from_secs(1);
+      let mut spool = self.spool.lock().unwrap();
+      if spool.payloads.front().is_some_and(|(it, _)| *it == id) {
+        spool.payloads.pop_front();
+      }
+      self.save();
+    }
+  }
+
+  /// the error and whether it's worth trying again
+  async fn post(&self, payload: &Payload) -> Result<(), (String, bool)> {
+    let mut req = self
+      .client
+      .post(&self.url)
+      .timeout(TIMEOUT)
+      .body(payload.body.clone());
+    if let Some(content_type) = &payload.content_type {
+      req = req.header(reqwest::header::CONTENT_TYPE, content_type);
+    }
+    if let Some(encoding) = &payload.content_encoding {
+      req = req.header(reqwest::header::CONTENT_ENCODING, encoding);
+    }
+    let res = req.send().await.map_err(|err| (err.to_string(), true))?;
+    let status = res.status();
+    if status.is_success() {
+      return Ok(());
+    }
+    let retryable = status.is_server_error()
+      || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+      || status == reqwest::StatusCode::REQUEST_TIMEOUT;
+    let body = res.text().await.unwrap_or_default();
+    Err((format!("{status}: {body}"), retryable))
+  }
+
+  /// written by [`Self::write_spool`], off the request
+  fn save(&self) {
+    if self.file.is_some() {
+      self.changed.notify_one();
+    }
+  }
+
+  /// rewritten whole, it's small, changes made while it's written are written
+  /// together after, see [`write`]
+  async fn write_spool(self: Arc<Self>) {
+    let Some(file) = self.file.clone() else {
+      return;
+    };
+    loop {
+      self.changed.notified().await;
+      let payloads = self
+        .spool
+        .lock()
+        .unwrap()
+        .payloads
+        .iter()
+        .map(|(_, it)| it.clone())
+        .collect::<Vec<_>>();
+      let file = file.clone();
+      let written =
+        tokio::task::spawn_blocking(move || write(&file, &payloads).map_err(|err| (file, err)))
+          .await;
+      if let Ok(Err((file, err))) = written {
+        error!("failed to write {}\n{err}", file.display());
+      }
+    }
+  }
+}
+
+/// to a file next to it that's then renamed over it, so a crash while writing
+/// doesn't leave it cut off
+fn write(file: &Path, payloads: &[Payload]) -> std::io::Result<()> {
+  let base64 = base64::engine::general_purpose::STANDARD;
+  let mut lines = vec![];
+  for payload in payloads {
+    let line = serde_json::to_vec(&Spooled {
+      content_type: payload.content_type.clone(),
+      content_encoding: payload.content_encoding.clone(),
+      body: base64.encode(&payload.body),
+      received: Some(payload.received.timestamp()),
+    })
+    .unwrap();
+    lines.extend(line);
+    lines.push(b'\n');
+  }
+  let temp = file.with_extension("jsonl.tmp");
+  let mut written = std::fs::File::create(&temp)?;
+  written.write_all(&lines)?;
+  written.sync_all()?;
+  std::fs::rename(&temp, file)
+}
+
+/// what a spool file holds, lines that don't parse are skipped
+fn load(file: &Path) -> Vec<Payload> {
+  let text = match std::fs::read_to_string(file) {
+    Ok(it) => it,
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => return vec![],
+    Err(err) => {
+      error!("failed to read {}\n{err}", file.display());
+      return vec![];
+    }
+  };
+  let base64 = base64::engine::general_purpose::STANDARD;
+  text
+    .lines()
+    .filter_map(|line| {
+      let spooled = serde_json::from_str::<Spooled>(line).ok()?;
+      Some(Payload {
+        content_type: spooled.content_type,
+        content_encoding: spooled.content_encoding,
+        body: base64.decode(spooled.body).ok()?.into(),
+        received: spooled
+          .received
+          .and_then(|it| Local.timestamp_opt(it, 0).single())
+          .unwrap_or_else(Local::now),
+      })
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn spool_files_are_replaced_whole() {
+    let dir = std::env::temp_dir().join(format!("relay-spool-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("host.jsonl");
+    let received = Local.timestamp_opt(1_700_000_000, 0).unwrap();
+    let payload = |body: &'static [u8]| Payload {
+      content_type: Some("application/json".to_string()),
+      content_encoding: None,
+      body: Bytes::from_static(body),
+      received,
+    };
+
+    write(&file, &[payload(b"a"), payload(b"b")]).unwrap();
+    write(&file, &[payload(b"c")]).unwrap();
+    let loaded = load(&file);
+    let temp_left = file.with_extension("jsonl.tmp").exists();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(loaded.len(), 1);
+    assert_eq!(loaded[0].body, Bytes::from_static(b"c"));
+    assert_eq!(loaded[0].received, received);
+    assert!(!temp_left);
+  }
+}
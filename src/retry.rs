@@ -0,0 +1,79 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::notifier::Message;
+
+/// wait before each retry, the last one repeating
+static BACKOFF: &[Duration] = &[
+  Duration::from_secs(1),
+  Duration::from_secs(5),
+  Duration::from_secs(30),
+  Duration::from_secs(60),
+];
+
+/// notifications that failed to send, retried until `give_up_after`
+pub struct RetryQueue {
+  /// 0 disables retrying
+  pub give_up_after: Duration,
+  pub capacity: usize,
+  pub pending: Mutex<VecDeque<Retry>>,
+}
+
+pub struct Retry {
+  /// [`Notifier::name`](crate::notifier::Notifier::name) of the one that failed
+  pub notifier: &'static str,
+  pub message: Message,
+  pub attempts: usize,
+  pub first_failed: Instant,
+  pub next_attempt: Instant,
+}
+
+impl Retry {
+  pub fn new(notifier: &'static str, message: Message) -> Self {
+    let now = Instant::now();
+    Self {
+      notifier,
+      message,
+      attempts: 0,
+      first_failed: now,
+      next_attempt: now + BACKOFF[0],
+    }
+  }
+
+  /// schedule the next attempt after one more failed
+  pub fn failed_again(&mut self) {
+    self.attempts += 1;
+    let delay = BACKOFF[self.attempts.min(BACKOFF.len() - 1)];
+    self.next_attempt = Instant::now() + delay;
+  }
+}
+
+impl RetryQueue {
+  pub fn enabled(&self) -> bool {
+    !self.give_up_after.is_zero()
+  }
+
+  /// returns false if the queue is full
+  pub fn push(&self, retry: Retry) -> bool {
+    let mut pending = self.pending.lock().unwrap();
+    if pending.len() >= self.capacity {
+      return false;
+    }
+    pending.push_back(retry);
+    true
+  }
+
+  pub fn take_due(&self) -> Vec<Retry> {
+    let mut pending = self.pending.lock().unwrap();
+    let now = Instant::now();
+    let mut due = vec![];
+    for retry in std::mem::take(&mut *pending) {
+      match retry.next_attempt <= now {
+        true => due.push(retry),
+        false => pending.push_back(retry),
+      }
+    }
+    due
+  }
+}
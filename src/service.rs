@@ -0,0 +1,254 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use tokio::sync::Notify;
+use windows::core::{HSTRING, PCWSTR, PWSTR};
+use windows::Win32::Foundation::{ERROR_CALL_NOT_IMPLEMENTED, NO_ERROR};
+use windows::Win32::System::Services::{
+  CloseServiceHandle, ControlService, CreateServiceW, DeleteService, OpenSCManagerW, OpenServiceW,
+  RegisterServiceCtrlHandlerExW, SetServiceStatus, StartServiceCtrlDispatcherW, SC_MANAGER_CONNECT,
+  SC_MANAGER_CREATE_SERVICE, SERVICE_ACCEPT_SHUTDOWN, SERVICE_ACCEPT_STOP, SERVICE_ALL_ACCESS,
+  SERVICE_AUTO_START, SERVICE_CONTROL_INTERROGATE, SERVICE_CONTROL_SHUTDOWN, SERVICE_CONTROL_STOP,
+  SERVICE_ERROR_NORMAL, SERVICE_RUNNING, SERVICE_STATUS, SERVICE_STATUS_CURRENT_STATE,
+  SERVICE_STATUS_HANDLE, SERVICE_STOPPED, SERVICE_STOP_PENDING, SERVICE_TABLE_ENTRYW,
+  SERVICE_WIN32_OWN_PROCESS,
+};
+
+use crate::{Args, Command, ServiceCommand, ServiceInstall};
+
+/// as registered with the service control manager
+static NAME: &str = "BiliBiliRecNotifier";
+static DISPLAY_NAME: &str = "BiliBili Rec Notifier";
+
+/// the log file of `service run`, next to the exe, when --log-file isn't given
+static LOG_FILE: &str = "bilibili_rec_notifier.log";
+
+/// how long a stop may take, as told to the service control manager
+static STOP_WAIT_HINT_MS: u32 = 15000;
+
+/// for [`service_main`], which the dispatcher calls without them
+static ARGS: Mutex<Option<Args>> = Mutex::new(None);
+
+static STATUS_HANDLE: OnceLock<SERVICE_STATUS_HANDLE> = OnceLock::new();
+
+fn stop() -> &'static Notify {
+  static STOP: OnceLock<Notify> = OnceLock::new();
+  STOP.get_or_init(Notify::new)
+}
+
+/// a stop or shutdown from the service control manager, never when not run
+/// as a service
+pub async fn stopped() {
+  stop().notified().await;
+}
+
+/// where `service run` logs to, there's no console to
+pub fn log_file(args: &Args) -> Option<PathBuf> {
+  let Some(Command::Service(service)) = &args.command else {
+    return None;
+  };
+  let ServiceCommand::Run(run) = &service.command else {
+    return None;
+  };
+  run.log_file.clone().or_else(|| {
+    let exe = std::env::current_exe().ok()?;
+    Some(exe.with_file_name(LOG_FILE))
+  })
+}
+
+/// registers the service to start at boot with this exe and the options
+/// before `service install`, run as `service run`
+pub fn install(install: &ServiceInstall) -> Result<(), String> {
+  let exe = std::env::current_exe().map_err(|err| format!("failed to find the exe\n{err}"))?;
+  let args = std::env::args_os().skip(1).collect::<Vec<_>>();
+  let options = args
+    .windows(2)
+    .position(|it| it[0] == "service" && it[1] == "install")
+    .map_or(&args[..], |it| &args[..it]);
+
+  let mut command_line = quote(exe.as_os_str().to_os_string());
+  for option in options {
+    command_line.push(' ');
+    command_line.push_str(&quote(option.clone()));
+  }
+  command_line.push_str(" service run");
+  if let Some(log_file) = &install.log_file {
+    command_line.push_str(" --log-file ");
+    command_line.push_str(&quote(log_file.as_os_str().to_os_string()));
+  }
+
+  let user = install.user.as_deref().map(HSTRING::from);
+  let password = install.password.as_deref().map(HSTRING::from);
+  let pcwstr = |it: &Option<HSTRING>| it.as_ref().map_or(PCWSTR::null(), |it| PCWSTR(it.as_ptr()));
+
+  unsafe {
+    let manager = OpenSCManagerW(PCWSTR::null(), PCWSTR::null(), SC_MANAGER_CREATE_SERVICE)
+      .map_err(|err| {
+        format!("failed to open the service manager, run this as administrator\n{err}")
+      })?;
+    let service = CreateServiceW(
+      manager,
+      &HSTRING::from(NAME),
+      &HSTRING::from(DISPLAY_NAME),
+      SERVICE_ALL_ACCESS,
+      SERVICE_WIN32_OWN_PROCESS,
+      SERVICE_AUTO_START,
+      SERVICE_ERROR_NORMAL,
+      &HSTRING::from(command_line.as_str()),
+      PCWSTR::null(),
+      std::ptr::null_mut(),
+      PCWSTR::null(),
+      pcwstr(&user),
+      pcwstr(&password),
+    );
+    CloseServiceHandle(manager);
+    let service = service.map_err(|err| format!("failed to create the {NAME} service\n{err}"))?;
+    CloseServiceHandle(service);
+  }
+  println!("installed the {NAME} service as {command_line}");
+  Ok(())
+}
+
+/// stops the service if it's running and removes it
+pub fn uninstall() -> Result<(), String> {
+  unsafe {
+    let manager =
+      OpenSCManagerW(PCWSTR::null(), PCWSTR::null(), SC_MANAGER_CONNECT).map_err(|err| {
+        format!("failed to open the service manager, run this as administrator\n{err}")
+      })?;
+    let service = OpenServiceW(manager, &HSTRING::from(NAME), SERVICE_ALL_ACCESS);
+    CloseServiceHandle(manager);
+    let service = service.map_err(|err| format!("failed to open the {NAME} service\n{err}"))?;
+    // it's only removed once stopped, fails when it isn't running
+    let mut status = SERVICE_STATUS::default();
+    ControlService(service, SERVICE_CONTROL_STOP, &mut status);
+    let deleted = DeleteService(service).ok();
+    CloseServiceHandle(service);
+    deleted.map_err(|err| format!("failed to delete the {NAME} service\n{err}"))?;
+  }
+  println!("uninstalled the {NAME} service");
+  Ok(())
+}
+
+/// hands the main thread to the service control manager, which runs the
+/// server through [`service_main`] on a thread of its own
+pub fn dispatch(args: Args) -> Result<(), String> {
+  *ARGS.lock().unwrap() = Some(args);
+  let mut name = NAME.encode_utf16().chain([0]).collect::<Vec<_>>();
+  let table = [
+    SERVICE_TABLE_ENTRYW {
+      lpServiceName: PWSTR(name.as_mut_ptr()),
+      lpServiceProc: Some(service_main),
+    },
+    SERVICE_TABLE_ENTRYW::default(),
+  ];
+  unsafe { StartServiceCtrlDispatcherW(table.as_ptr()) }
+    .ok()
+    .map_err(|err| {
+      format!(
+        "failed to start as a service, `service run` is for the service manager to run\n{err}"
+      )
+    })
+}
+
+unsafe extern "system" fn service_main(_argc: u32, _argv: *mut PWSTR) {
+  let handle = match RegisterServiceCtrlHandlerExW(
+    &HSTRING::from(NAME),
+    Some(handle_control),
+    std::ptr::null(),
+  ) {
+    Ok(it) => it,
+    Err(_) => return,
+  };
+  let _ = STATUS_HANDLE.set(handle);
+  set_status(SERVICE_RUNNING);
+  if let Some(args) = ARGS.lock().unwrap().take() {
+    crate::start(args);
+  }
+  set_status(SERVICE_STOPPED);
+}
+
+/// stop and shutdown go the way ctrl c does
+unsafe extern "system" fn handle_control(
+  control: u32,
+  _event_type: u32,
+  _event_data: *mut std::ffi::c_void,
+  _context: *mut std::ffi::c_void,
+) -> u32 {
+  match control {
+    SERVICE_CONTROL_STOP | SERVICE_CONTROL_SHUTDOWN => {
+      set_status(SERVICE_STOP_PENDING);
+      stop().notify_one();
+      NO_ERROR.0
+    }
+    SERVICE_CONTROL_INTERROGATE => NO_ERROR.0,
+    _ => ERROR_CALL_NOT_IMPLEMENTED.0,
+  }
+}
+
+fn set_status(state: SERVICE_STATUS_CURRENT_STATE) {
+  let Some(handle) = STATUS_HANDLE.get() else {
+    return;
+  };
+  let status = SERVICE_STATUS {
+    dwServiceType: SERVICE_WIN32_OWN_PROCESS,
+    dwCurrentState: state,
+    dwControlsAccepted: match state {
+      SERVICE_RUNNING => SERVICE_ACCEPT_STOP | SERVICE_ACCEPT_SHUTDOWN,
+      _ => 0,
+    },
+    dwWaitHint: match state {
+      SERVICE_STOP_PENDING => STOP_WAIT_HINT_MS,
+      _ => 0,
+    },
+    ..Default::default()
+  };
+  unsafe {
+    SetServiceStatus(*handle, &status);
+  }
+}
+
+/// for the service's command line, in quotes when it has a space or a quote,
+/// split back as CommandLineToArgvW does, where backslashes only escape before
+/// a quote, the closing one included
+fn quote(arg: OsString) -> String {
+  let arg = arg.to_string_lossy().into_owned();
+  if !arg.is_empty() && !arg.contains([' ', '\t', '"']) {
+    return arg;
+  }
+  let mut quoted = String::from('"');
+  let mut backslashes = 0;
+  for c in arg.chars() {
+    if c == '\\' {
+      backslashes += 1;
+      continue;
+    }
+    let escaped = match c {
+      '"' => backslashes * 2 + 1,
+      _ => backslashes,
+    };
+    quoted.extend(std::iter::repeat_n('\\', escaped));
+    quoted.push(c);
+    backslashes = 0;
+  }
+  quoted.extend(std::iter::repeat_n('\\', backslashes * 2));
+  quoted.push('"');
+  quoted
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn quotes_as_windows_splits() {
+    let quoted = |it: &str| quote(OsString::from(it));
+    assert_eq!(quoted(r"C:\dir\exe.exe"), r"C:\dir\exe.exe");
+    assert_eq!(quoted(""), r#""""#);
+    assert_eq!(quoted(r"C:\my dir\"), r#""C:\my dir\\""#);
+    assert_eq!(quoted(r#"say "hi""#), r#""say \"hi\"""#);
+    assert_eq!(quoted(r#"a\"b c"#), r#""a\\\"b c""#);
+    assert_eq!(quoted(r"a\\b c"), r#""a\\b c""#);
+  }
+}
@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use futures_util::stream;
+use hyper::body::Bytes;
+use hyper::Body;
+use tokio::sync::{broadcast, watch};
+use tokio::time::{interval_at, Instant, Interval};
+use tracing::warn;
+
+use crate::Event;
+
+/// events a slow client may fall behind by before it misses some
+static BACKLOG: usize = 100;
+
+/// sent as a comment this often, so proxies keep idle connections open
+static KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// processed events for the clients of `GET /stream`
+pub struct EventFeed {
+  sender: broadcast::Sender<String>,
+  closed: watch::Sender<bool>,
+}
+
+impl Default for EventFeed {
+  fn default() -> Self {
+    Self {
+      sender: broadcast::channel(BACKLOG).0,
+      closed: watch::channel(false).0,
+    }
+  }
+}
+
+impl EventFeed {
+  /// the event as json with its `Decision` added, like in --record-events
+  pub fn publish(&self, event: &Event, decision: &str) {
+    // nobody listening isn't an error
+    if self.sender.receiver_count() == 0 {
+      return;
+    }
+    let mut line = serde_json::to_value(event).unwrap();
+    line["Decision"] = decision.into();
+    let _ = self.sender.send(line.to_string());
+  }
+
+  /// end every stream, on shutdown
+  pub fn close(&self) {
+    self.closed.send_replace(true);
+  }
+
+  /// a server-sent events body, dropped by hyper with the connection, which
+  /// unsubscribes it
+  pub fn subscribe(&self) -> Body {
+    let receiver = self.sender.subscribe();
+    let closed = self.closed.subscribe();
+    let keep_alive = interval_at(Instant::now() + KEEP_ALIVE, KEEP_ALIVE);
+    Body::wrap_stream(stream::unfold(
+      (receiver, closed, keep_alive),
+      |(mut receiver, mut closed, mut keep_alive)| async move {
+        if *closed.borrow() {
+          return None;
+        }
+        let frame = tokio::select! {
+          frame = next_frame(&mut receiver, &mut keep_alive) => frame?,
+          _ = closed.changed() => return None,
+        };
+        Some((Ok::<_, hyper::Error>(frame), (receiver, closed, keep_alive)))
+      },
+    ))
+  }
+}
+
+async fn next_frame(
+  receiver: &mut broadcast::Receiver<String>,
+  keep_alive: &mut Interval,
+) -> Option<Bytes> {
+  tokio::select! {
+    line = receiver.recv() => match line {
+      Ok(line) => Some(Bytes::from(format!("data: {line}\n\n"))),
+      Err(broadcast::error::RecvError::Lagged(missed)) => {
+        warn!("a /stream client fell behind, it missed {missed} events");
+        Some(Bytes::from(format!(": missed {missed} events\n\n")))
+      }
+      Err(broadcast::error::RecvError::Closed) => None,
+    },
+    _ = keep_alive.tick() => Some(Bytes::from_static(b": keep-alive\n\n")),
+  }
+}
@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use futures_util::future::join_all;
+use hyper::StatusCode;
+
+use crate::auth::{self, IpRange, RecorderAuth};
+use crate::config::{Config, ConfigPath, NotifierConfig};
+use crate::cooldown::Cooldown;
+use crate::debounce::Debouncer;
+use crate::dedupe::Dedupe;
+use crate::desktop::{DaemonStatus, DesktopOptions, Fallback, StartNotifications};
+use crate::digest::StartDigest;
+use crate::export::EventLog;
+use crate::filter::{AreaFilter, Combine};
+use crate::flag::RoomFlag;
+use crate::forward::{self, ForwardTarget};
+use crate::hook::EventCommands;
+use crate::i18n::Lang;
+use crate::images::ImageCache;
+use crate::metrics::Metrics;
+use crate::mqtt::Mqtt;
+use crate::notifier::{self, Message, Notifier};
+use crate::opener::AutoOpen;
+use crate::player::Player;
+use crate::poll::LiveRooms;
+use crate::quiet::Quiet;
+use crate::rate_limit::RateLimit;
+use crate::record::EventRecorder;
+use crate::relay::RelayTarget;
+use crate::retry::RetryQueue;
+use crate::sse::EventFeed;
+use crate::stats::StreamHistory;
+use crate::tasks::Tasks;
+use crate::template::{Engine, Templates};
+use crate::title::TitleWatch;
+use crate::tts::Tts;
+use crate::uptime::StreamStarts;
+use crate::version;
+use crate::Event;
+
+pub struct AppState {
+  /// room filters are per listener
+  pub area_filter: Option<AreaFilter>,
+  pub filter_combine: Combine,
+  pub notify_events: Vec<String>,
+  pub notify_on_start: bool,
+  /// required as `?token=` or a bearer token, when set
+  pub token: Option<String>,
+  pub auth: RecorderAuth,
+  /// clients that may connect, empty is anyone
+  pub allow_ip: Vec<IpRange>,
+  pub desktop: Arc<DesktopOptions>,
+  /// where notifications go, the desktop first unless --no-desktop-notify
+  pub notifiers: Vec<Box<dyn Notifier>>,
+  /// --notifier-chain, by name, left out of [`Self::send_all`]
+  pub notifier_chain: Vec<String>,
+  pub auto_open: Option<AutoOpen>,
+  pub player: Option<Player>,
+  pub tts: Option<Tts>,
+  pub event_commands: EventCommands,
+  /// unless neither avatars nor covers are shown
+  pub images: Option<Arc<ImageCache>>,
+  /// in chars, 0 is unlimited
+  pub max_body_len: usize,
+  pub start_notifications: Arc<StartNotifications>,
+  /// respond before notifying
+  pub async_ack: bool,
+  /// notifications sent after responding
+  pub tasks: Tasks,
+  pub cooldown: Cooldown,
+  pub dedupe: Dedupe,
+  pub quiet: Option<Quiet>,
+  pub success_status: StatusCode,
+  pub success_response: String,
+  pub start_digest: StartDigest,
+  /// --max-notifications
+  pub rate_limit: Option<RateLimit>,
+  pub metrics: Metrics,
+  pub daemon: DaemonStatus,
+  /// --record-events
+  pub recorder: Option<EventRecorder>,
+  /// --mqtt-url
+  pub mqtt: Option<Mqtt>,
+  /// --relay-url
+  pub relays: Vec<Arc<RelayTarget>>,
+  /// when the last event, besides polled ones, came in, or the start, for
+  /// --watchdog-secs
+  pub last_event: Mutex<Instant>,
+  /// --notify-title-change
+  pub titles: Option<TitleWatch>,
+  /// for how long a stream ran, set as the Duration of its StreamEnded
+  pub stream_starts: StreamStarts,
+  /// --restream-window-secs
+  pub restream_window: Option<Duration>,
+  /// with --poll-rooms, to notify a start once when both the recorder and a
+  /// poll report it
+  pub live_rooms: Option<LiveRooms>,
+  /// for GET /stream
+  pub feed: EventFeed,
+  /// for /stats
+  pub history: StreamHistory,
+  /// for /events.csv
+  pub event_log: EventLog,
+  /// --notify-danmaku
+  pub danmaku: Option<RoomFlag>,
+  /// --notify-problems, the danmaku connection of each room
+  pub problems: Option<RoomFlag>,
+  /// --notify-recording-start
+  pub recording: Option<RoomFlag>,
+  /// only log what would be notified
+  pub dry_run: bool,
+  pub stdout_json: bool,
+  /// --log-raw-on-error
+  pub log_raw_on_error: bool,
+  /// --form-field
+  pub form_field: String,
+  /// --max-event-age-secs, `None` notifies events of any age
+  pub max_event_age: Option<Duration>,
+  /// --keepalive-secs
+  pub keepalive: Option<Duration>,
+  /// --request-timeout-secs
+  pub request_timeout: Option<Duration>,
+  /// --header-read-timeout-secs
+  pub header_read_timeout: Duration,
+  pub no_desktop_notify: bool,
+  pub fallback: Fallback,
+  pub retries: RetryQueue,
+  /// pending StreamStarted notifications, called off by a StreamEnded
+  pub start_debouncer: Debouncer,
+  pub config_source: ConfigSource,
+  /// swapped on reload
+  pub templates: RwLock<Arc<Templates>>,
+  /// by notifier name, swapped on reload
+  pub notifier_filters: RwLock<Arc<HashMap<String, NotifierConfig>>>,
+  /// swapped on reload
+  pub forward_targets: RwLock<Arc<Vec<ForwardTarget>>>,
+}
+
+/// everything the reloadable part of the state is built from
+#[derive(Debug)]
+pub struct ConfigSource {
+  /// --config, merged in order
+  pub paths: Vec<ConfigPath>,
+  pub engine: Engine,
+  pub lang: Lang,
+  pub headline: Option<String>,
+  pub summary: Option<String>,
+  pub body: Option<String>,
+  pub forward_urls: Vec<String>,
+}
+
+/// the reloadable part of the state
+pub struct Loaded {
+  pub templates: Templates,
+  pub notifier_filters: HashMap<String, NotifierConfig>,
+  pub forward_targets: Vec<ForwardTarget>,
+}
+
+impl ConfigSource {
+  pub fn load(&self) -> Result<Loaded, String> {
+    let config = Config::load_all(&self.paths)?;
+    let templates = Templates::build(
+      self.engine,
+      self.lang,
+      self.headline.as_deref(),
+      self.summary.as_deref(),
+      self.body.as_deref(),
+      &config.templates,
+    )?;
+    let forward_targets = forward::targets(&self.forward_urls, self.engine, &config.forward)?;
+    Ok(Loaded {
+      templates,
+      notifier_filters: config.notifiers,
+      forward_targets,
+    })
+  }
+}
+
+impl AppState {
+  pub fn templates(&self) -> Arc<Templates> {
+    self.templates.read().unwrap().clone()
+  }
+
+  /// re read the config file, the current one is kept if it's invalid
+  pub fn reload(&self) -> Result<(), String> {
+    let loaded = self.config_source.load()?;
+    *self.templates.write().unwrap() = Arc::new(loaded.templates);
+    *self.notifier_filters.write().unwrap() = Arc::new(loaded.notifier_filters);
+    *self.forward_targets.write().unwrap() = Arc::new(loaded.forward_targets);
+    Ok(())
+  }
+
+  /// the metrics' health, with how the relay targets are doing and the build
+  pub fn health(&self) -> serde_json::Value {
+    let mut health = self.metrics.health();
+    health["build"] = version::json();
+    if !self.relays.is_empty() {
+      health["relays"] = self.relays.iter().map(|it| it.status()).collect();
+    }
+    health
+  }
+
+  /// post the event to every --forward-url after responding
+  pub fn forward(&self, event: &Event) {
+    let targets = self.forward_targets.read().unwrap().clone();
+    if targets.is_empty() {
+      return;
+    }
+    let event = event.clone();
+    self.tasks.spawn(async move {
+      join_all(targets.iter().map(|it| it.send(&event))).await;
+    });
+  }
+
+  /// whether a message passes the filters of the named notifier
+  pub fn wants(&self, notifier: &str, message: &Message) -> bool {
+    let filters = self.notifier_filters.read().unwrap().clone();
+    notifier::passes(filters.get(notifier), message)
+  }
+
+  /// whether the named notifier is only sent to as part of --notifier-chain
+  pub fn chained(&self, notifier: &str) -> bool {
+    self.notifier_chain.iter().any(|it| it == notifier)
+  }
+
+  /// send through the notifiers that aren't chained
+  pub async fn send_all(&self, message: &Message) -> Vec<(&'static str, Result<(), String>)> {
+    let filters = self.notifier_filters.read().unwrap().clone();
+    notifier::send_all(
+      &self.notifiers,
+      &self.notifier_chain,
+      &filters,
+      &self.metrics,
+      message,
+    )
+    .await
+  }
+
+  /// before exiting, so batched messages aren't lost
+  pub async fn flush_notifiers(&self) {
+    join_all(self.notifiers.iter().map(|it| it.flush())).await;
+  }
+
+  pub fn allowed(&self, ip: IpAddr) -> bool {
+    self.allow_ip.is_empty() || self.allow_ip.iter().any(|it| it.contains(ip))
+  }
+
+  /// whether the request carries the token, always true without one, the
+  /// signature of [`RecorderAuth::Hmac`] is checked by [`Self::signed`]
+  pub fn authorized(
+    &self,
+    query: Option<&str>,
+    authorization: Option<&str>,
+    webhook: bool,
+  ) -> bool {
+    let Some(token) = &self.token else {
+      return true;
+    };
+    match self.auth {
+      RecorderAuth::Token => auth::token_matches(token, query, authorization),
+      RecorderAuth::Basic => auth::basic_matches(token, authorization),
+      RecorderAuth::Hmac if webhook => true,
+      RecorderAuth::Hmac => auth::token_matches(token, query, authorization),
+    }
+  }
+
+  /// whether a webhook body is signed as --recorder-auth hmac wants it
+  pub fn signed(&self, signature: Option<&str>, body: &[u8]) -> bool {
+    match (&self.token, self.auth) {
+      (Some(token), RecorderAuth::Hmac) => auth::signature_matches(token, signature, body),
+      _ => true,
+    }
+  }
+}
@@ -0,0 +1,201 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+
+use chrono::{DateTime, FixedOffset, Local};
+use serde::Serialize;
+
+use crate::uptime::MAX_STREAM;
+use crate::Event;
+
+/// stream starts and ends kept for /stats, the oldest are dropped past this
+static MAX_ENTRIES: usize = 10000;
+
+/// the StreamStarted and StreamEnded events processed, for `GET /stats`
+#[derive(Default)]
+pub struct StreamHistory {
+  entries: Mutex<VecDeque<Entry>>,
+}
+
+struct Entry {
+  room_id: i64,
+  name: String,
+  at: DateTime<FixedOffset>,
+  kind: Kind,
+}
+
+enum Kind {
+  Started {
+    area: Area,
+  },
+  /// with how long the stream ran, when that's known
+  Ended {
+    secs: Option<f64>,
+  },
+}
+
+#[derive(Serialize, Clone, PartialEq, Eq, Hash)]
+pub struct Area {
+  pub parent: String,
+  pub child: String,
+}
+
+/// the `from` and `to` query params of /stats, as RFC 3339 timestamps, each
+/// end is open when not given
+#[derive(Default)]
+pub struct Range {
+  from: Option<DateTime<FixedOffset>>,
+  to: Option<DateTime<FixedOffset>>,
+}
+
+impl Range {
+  pub fn from_query(query: Option<&str>) -> Result<Self, String> {
+    Self::from_params(query, "from", "to")
+  }
+
+  /// with the bounds in params of other names, the other params, like a
+  /// token, are left alone
+  pub fn from_params(query: Option<&str>, from: &str, to: &str) -> Result<Self, String> {
+    let mut range = Self::default();
+    for (name, value) in form_urlencoded::parse(query.unwrap_or_default().as_bytes()) {
+      let bound = match name.as_ref() {
+        it if it == from => &mut range.from,
+        it if it == to => &mut range.to,
+        _ => continue,
+      };
+      *bound =
+        Some(DateTime::parse_from_rfc3339(&value).map_err(|err| {
+          format!("invalid `{name}` `{value}`, expected an RFC 3339 time: {err}")
+        })?);
+    }
+    Ok(range)
+  }
+
+  pub fn contains(&self, at: DateTime<FixedOffset>) -> bool {
+    self.from.is_none_or(|it| at >= it) && self.to.is_none_or(|it| at < it)
+  }
+}
+
+#[derive(Serialize)]
+struct Stats {
+  from: Option<String>,
+  to: Option<String>,
+  /// by room id
+  rooms: Vec<RoomStats>,
+}
+
+#[derive(Serialize)]
+struct RoomStats {
+  room_id: i64,
+  /// as of the room's last event in range
+  name: String,
+  /// starts in range
+  streams: u64,
+  /// the durations of streams ended in range, and how long the one still
+  /// running has been, unless its end was likely missed
+  live_secs: u64,
+  last_start: Option<String>,
+  last_end: Option<String>,
+  /// the area the room started streaming in most, the latest on a tie
+  top_area: Option<Area>,
+}
+
+#[derive(Default)]
+struct Totals {
+  name: String,
+  streams: u64,
+  live_secs: f64,
+  last_start: Option<DateTime<FixedOffset>>,
+  last_end: Option<DateTime<FixedOffset>>,
+  /// how often, and the index of the latest
+  areas: HashMap<Area, (u64, usize)>,
+}
+
+impl StreamHistory {
+  /// other event types aren't kept
+  pub fn add(&self, event: &Event, at: DateTime<FixedOffset>) {
+    let data = &event.event_data;
+    let kind = match event.event_type.as_str() {
+      "StreamStarted" => Kind::Started {
+        area: Area {
+          parent: data.area_name_parent.clone(),
+          child: data.area_name_child.clone(),
+        },
+      },
+      "StreamEnded" => Kind::Ended {
+        secs: data.duration,
+      },
+      _ => return,
+    };
+    let mut entries = self.entries.lock().unwrap();
+    if entries.len() >= MAX_ENTRIES {
+      entries.pop_front();
+    }
+    entries.push_back(Entry {
+      room_id: data.room_id,
+      name: data.name.clone(),
+      at,
+      kind,
+    });
+  }
+
+  /// the per room totals of the events in `range`, in one pass
+  pub fn stats(&self, range: &Range) -> serde_json::Value {
+    let mut rooms = BTreeMap::<i64, Totals>::new();
+    for (i, entry) in self.entries.lock().unwrap().iter().enumerate() {
+      if !range.contains(entry.at) {
+        continue;
+      }
+      let totals = rooms.entry(entry.room_id).or_default();
+      totals.name.clone_from(&entry.name);
+      match &entry.kind {
+        Kind::Started { area } => {
+          totals.streams += 1;
+          totals.last_start = Some(entry.at);
+          let (count, latest) = totals.areas.entry(area.clone()).or_default();
+          *count += 1;
+          *latest = i;
+        }
+        Kind::Ended { secs } => {
+          totals.live_secs += secs.unwrap_or_default();
+          totals.last_end = Some(entry.at);
+        }
+      }
+    }
+
+    let now = Local::now().fixed_offset();
+    let until = range.to.map_or(now, |it| it.min(now));
+    let rooms = rooms
+      .into_iter()
+      .map(|(room_id, totals)| {
+        let mut live_secs = totals.live_secs;
+        if let Some(start) = totals.last_start {
+          let running = (until - start).to_std().unwrap_or_default();
+          if totals.last_end.is_none_or(|it| it < start) && running < MAX_STREAM {
+            live_secs += running.as_secs_f64();
+          }
+        }
+        let top_area = totals
+          .areas
+          .into_iter()
+          .max_by_key(|(_, it)| *it)
+          .map(|(area, _)| area);
+        RoomStats {
+          room_id,
+          name: totals.name,
+          streams: totals.streams,
+          live_secs: live_secs as u64,
+          last_start: totals.last_start.map(|it| it.to_rfc3339()),
+          last_end: totals.last_end.map(|it| it.to_rfc3339()),
+          top_area,
+        }
+      })
+      .collect();
+
+    serde_json::to_value(Stats {
+      from: range.from.map(|it| it.to_rfc3339()),
+      to: range.to.map(|it| it.to_rfc3339()),
+      rooms,
+    })
+    .unwrap()
+  }
+}
@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use tracing::warn;
+
+/// tells systemd how the service is doing, for `Type=notify` units, does
+/// nothing when not started by one, as `NOTIFY_SOCKET` isn't set then
+#[cfg(unix)]
+pub fn notify(state: &str) {
+  use std::os::unix::net::UnixDatagram;
+
+  let Some(path) = std::env::var_os("NOTIFY_SOCKET") else {
+    return;
+  };
+  let result = UnixDatagram::unbound().and_then(|socket| {
+    // systemd's own socket is in the abstract namespace
+    #[cfg(target_os = "linux")]
+    if let Some(name) = path.to_str().and_then(|it| it.strip_prefix('@')) {
+      use std::os::linux::net::SocketAddrExt;
+      let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+      return socket.send_to_addr(state.as_bytes(), &addr);
+    }
+    socket.send_to(state.as_bytes(), &path)
+  });
+  if let Err(err) = result {
+    warn!("failed to notify systemd of {state}\n{err}");
+  }
+}
+
+#[cfg(not(unix))]
+pub fn notify(_state: &str) {}
+
+/// how often systemd wants to hear from the service, when the unit has
+/// `WatchdogSec=` set and it's meant for this process
+pub fn watchdog_interval() -> Option<Duration> {
+  std::env::var_os("NOTIFY_SOCKET")?;
+  let usec = std::env::var("WATCHDOG_USEC").ok()?.parse::<u64>().ok()?;
+  let pid = std::env::var("WATCHDOG_PID").ok();
+  if pid.is_some_and(|it| it != std::process::id().to_string()) {
+    return None;
+  }
+  (usec > 0).then(|| Duration::from_micros(usec))
+}
+
+/// pings the watchdog at half its interval while the runtime still gets a
+/// task spawned and done in time, so systemd restarts a process that hung
+pub async fn keep_watchdog(interval: Duration) {
+  let mut ticks = tokio::time::interval(interval / 2);
+  loop {
+    ticks.tick().await;
+    let alive = tokio::time::timeout(interval / 2, tokio::spawn(async {})).await;
+    if matches!(alive, Ok(Ok(()))) {
+      notify("WATCHDOG=1");
+    } else {
+      warn!("the runtime didn't get to a task in time, not pinging the watchdog");
+    }
+  }
+}
@@ -0,0 +1,28 @@
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use futures_util::future::join_all;
+use tokio::task::JoinHandle;
+
+/// background tasks that shutdown waits for
+#[derive(Default)]
+pub struct Tasks(Mutex<Vec<JoinHandle<()>>>);
+
+impl Tasks {
+  pub fn spawn<F>(&self, task: F)
+  where
+    F: Future<Output = ()> + Send + 'static,
+  {
+    let mut tasks = self.0.lock().unwrap();
+    tasks.retain(|it| !it.is_finished());
+    tasks.push(tokio::spawn(task));
+  }
+
+  /// wait for the running tasks for at most `limit`, returns whether they all
+  /// finished
+  pub async fn wait(&self, limit: Duration) -> bool {
+    let tasks = std::mem::take(&mut *self.0.lock().unwrap());
+    tokio::time::timeout(limit, join_all(tasks)).await.is_ok()
+  }
+}
@@ -0,0 +1,533 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::Chars;
+use std::str::FromStr;
+
+use chrono::{DateTime, Local};
+use handlebars::Handlebars;
+
+use crate::config::TemplateConfig;
+use crate::i18n::Lang;
+use crate::Event;
+
+/// event types the recorder sends
+pub static EVENT_TYPES: &[&str] = &[
+  "SessionStarted",
+  "SessionEnded",
+  "FileOpening",
+  "FileClosed",
+  "StreamStarted",
+  "StreamEnded",
+];
+
+/// titles longer than this many chars are cut with an ellipsis
+static MAX_TITLE_CHARS: usize = 60;
+
+/// which syntax the notification templates are written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+  /// `{field}` placeholders
+  Simple,
+  /// handlebars, with the event data as context
+  Handlebars,
+}
+
+impl FromStr for Engine {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "simple" => Ok(Self::Simple),
+      "handlebars" => Ok(Self::Handlebars),
+      _ => Err(format!(
+        "unknown template engine `{s}`, expected simple or handlebars"
+      )),
+    }
+  }
+}
+
+/// summary and body for one kind of event
+#[derive(Debug, Clone)]
+pub struct TemplateSet {
+  pub summary: Template,
+  pub body: Template,
+  /// either is from the config or the command line, they're left as they
+  /// render then, without the durations the built in ones get
+  pub custom: bool,
+}
+
+/// the templates for every event type
+#[derive(Debug)]
+pub struct Templates {
+  by_type: HashMap<String, TemplateSet>,
+  fallback: TemplateSet,
+}
+
+impl Templates {
+  /// a template from the config table for the event type wins over the
+  /// command line one, which wins over the built in one for that type in
+  /// `lang`, `headline` replaces the built in StreamStarted summary
+  pub fn build(
+    engine: Engine,
+    lang: Lang,
+    headline: Option<&str>,
+    summary: Option<&str>,
+    body: Option<&str>,
+    table: &HashMap<String, TemplateConfig>,
+  ) -> Result<Self, String> {
+    if let Some(event_type) = table.keys().find(|it| !EVENT_TYPES.contains(&it.as_str())) {
+      return Err(format!("[templates.{event_type}]: unknown event type"));
+    }
+
+    let mut by_type = HashMap::new();
+    for event_type in EVENT_TYPES {
+      let (mut default_summary, default_body) = lang.templates(event_type);
+      if let (&"StreamStarted", Some(headline)) = (event_type, headline) {
+        default_summary = headline;
+      }
+      let configured = table.get(*event_type);
+      let custom = summary.is_some()
+        || body.is_some()
+        || configured.is_some_and(|it| it.summary.is_some() || it.body.is_some());
+      let set = TemplateSet {
+        summary: resolve(
+          engine,
+          Some(event_type),
+          "summary",
+          configured.and_then(|it| it.summary.as_deref()),
+          summary,
+          default_summary,
+        )?,
+        body: resolve(
+          engine,
+          Some(event_type),
+          "body",
+          configured.and_then(|it| it.body.as_deref()),
+          body,
+          default_body,
+        )?,
+        custom,
+      };
+      by_type.insert(event_type.to_string(), set);
+    }
+    let (default_summary, default_body) = lang.templates("");
+    let fallback = TemplateSet {
+      summary: resolve(engine, None, "summary", None, summary, default_summary)?,
+      body: resolve(engine, None, "body", None, body, default_body)?,
+      custom: summary.is_some() || body.is_some(),
+    };
+
+    Ok(Self { by_type, fallback })
+  }
+
+  pub fn get(&self, event_type: &str) -> &TemplateSet {
+    self.by_type.get(event_type).unwrap_or(&self.fallback)
+  }
+}
+
+fn resolve(
+  engine: Engine,
+  event_type: Option<&str>,
+  name: &str,
+  configured: Option<&str>,
+  cli: Option<&str>,
+  default: &str,
+) -> Result<Template, String> {
+  match (configured, cli) {
+    (Some(src), _) => Template::parse_with(engine, src, event_type).map_err(|err| {
+      format!(
+        "invalid {name} template for {}: {err}",
+        event_type.unwrap_or("")
+      )
+    }),
+    // the command line one is used for every event type
+    (None, Some(src)) => Template::parse_with(engine, src, None)
+      .map_err(|err| format!("invalid --template-{name}: {err}")),
+    // the built in default is always a simple template, whichever engine is selected
+    (None, None) => Template::parse(default, event_type)
+      .map_err(|err| format!("invalid {name} for {}: {err}", event_type.unwrap_or(""))),
+  }
+}
+
+/// a notification text, checked when parsed
+#[derive(Debug, Clone)]
+pub struct Template(Kind);
+
+#[derive(Debug, Clone)]
+enum Kind {
+  Simple(Vec<Segment>),
+  Handlebars(Box<Handlebars<'static>>),
+}
+
+static HANDLEBARS_NAME: &str = "template";
+
+#[derive(Debug, Clone)]
+enum Segment {
+  Literal(String),
+  Field(Field),
+  /// `{?...}`, left out when a field in it is empty or zero
+  Optional(Vec<Segment>),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Field {
+  Name,
+  Title,
+  RoomId,
+  ShortId,
+  AreaParent,
+  AreaChild,
+  Time,
+  RelativePath,
+  FileSize,
+  Duration,
+}
+
+impl Field {
+  fn from_name(name: &str) -> Option<Self> {
+    Some(match name {
+      "name" => Self::Name,
+      "title" => Self::Title,
+      "room_id" => Self::RoomId,
+      "short_id" => Self::ShortId,
+      "area_parent" => Self::AreaParent,
+      "area_child" => Self::AreaChild,
+      "time" => Self::Time,
+      "relative_path" => Self::RelativePath,
+      "file_size" => Self::FileSize,
+      "duration" => Self::Duration,
+      _ => return None,
+    })
+  }
+
+  /// empty, or zero for numbers, so an optional section with it is left out
+  fn is_empty(self, event: &Event) -> bool {
+    let data = &event.event_data;
+    match self {
+      Self::Name => data.name.is_empty(),
+      Self::Title => data.title.is_empty(),
+      Self::RoomId => data.room_id == 0,
+      Self::ShortId => data.short_id == 0,
+      Self::AreaParent => data.area_name_parent.is_empty(),
+      Self::AreaChild => data.area_name_child.is_empty(),
+      Self::Time => false,
+      Self::RelativePath => data.relative_path.as_deref().unwrap_or("").is_empty(),
+      Self::FileSize => data.file_size.is_none(),
+      Self::Duration => data.duration.is_none(),
+    }
+  }
+
+  /// whether events of this type carry the field, `None` means any type
+  fn available_for(self, event_type: Option<&str>) -> bool {
+    match self {
+      Self::RelativePath => matches!(event_type, Some("FileOpening" | "FileClosed")),
+      Self::FileSize | Self::Duration => event_type == Some("FileClosed"),
+      _ => true,
+    }
+  }
+}
+
+impl Template {
+  /// parse a template used for `event_type`, or for any type when `None`
+  pub fn parse_with(engine: Engine, src: &str, event_type: Option<&str>) -> Result<Self, String> {
+    match engine {
+      Engine::Simple => Self::parse(src, event_type),
+      Engine::Handlebars => Self::parse_handlebars(src, event_type),
+    }
+  }
+
+  /// parse a simple template, `{{` and `}}` are literal braces and `{?...}`
+  /// is only rendered when the fields in it, outside of the optional sections
+  /// within, aren't empty or zero
+  pub fn parse(src: &str, event_type: Option<&str>) -> Result<Self, String> {
+    Ok(Self(Kind::Simple(parse_segments(src, event_type)?)))
+  }
+
+  /// compile a handlebars template, then render it once against a sample
+  /// event so references to fields that don't exist fail here
+  fn parse_handlebars(src: &str, event_type: Option<&str>) -> Result<Self, String> {
+    let mut registry = Handlebars::new();
+    registry.set_strict_mode(true);
+    registry.register_escape_fn(handlebars::no_escape);
+    registry
+      .register_template_string(HANDLEBARS_NAME, src)
+      .map_err(|err| err.to_string())?;
+    registry
+      .render(HANDLEBARS_NAME, &sample_event(event_type).event_data)
+      .map_err(|err| err.to_string())?;
+
+    Ok(Self(Kind::Handlebars(Box::new(registry))))
+  }
+
+  pub fn render(&self, event: &Event) -> String {
+    match &self.0 {
+      Kind::Simple(segments) => render_simple(segments, event, false),
+      Kind::Handlebars(registry) => render_handlebars(registry, event),
+    }
+  }
+
+  /// for a json document, each value escaped to go inside a json string, titles
+  /// aren't cut so an escape sequence isn't either
+  pub fn render_json(&self, event: &Event) -> String {
+    match &self.0 {
+      Kind::Simple(segments) => render_simple(segments, event, true),
+      Kind::Handlebars(registry) => render_handlebars(registry, &json_escaped(event)),
+    }
+  }
+}
+
+fn render_handlebars(registry: &Handlebars, event: &Event) -> String {
+  registry
+    .render(HANDLEBARS_NAME, &event.event_data)
+    .unwrap_or_else(|err| {
+      tracing::error!("failed to render template\n{err:#?}");
+      String::new()
+    })
+}
+
+/// `text` as it goes between the quotes of a json string
+fn json_escape(text: &str) -> String {
+  let quoted = serde_json::to_string(text).unwrap();
+  quoted[1..quoted.len() - 1].to_string()
+}
+
+/// the event with its text escaped with [`json_escape`]
+fn json_escaped(event: &Event) -> Event {
+  let mut event = event.clone();
+  let data = &mut event.event_data;
+  data.name = json_escape(&data.name);
+  data.title = json_escape(&data.title);
+  data.area_name_parent = json_escape(&data.area_name_parent);
+  data.area_name_child = json_escape(&data.area_name_child);
+  data.relative_path = data.relative_path.as_deref().map(json_escape);
+  event
+}
+
+fn parse_segments(src: &str, event_type: Option<&str>) -> Result<Vec<Segment>, String> {
+  let mut segments = vec![];
+  let mut literal = String::new();
+  let mut chars = src.chars().peekable();
+
+  while let Some(ch) = chars.next() {
+    match ch {
+      '{' if chars.peek() == Some(&'{') => {
+        chars.next();
+        literal.push('{');
+      }
+      '}' if chars.peek() == Some(&'}') => {
+        chars.next();
+        literal.push('}');
+      }
+      '{' if chars.peek() == Some(&'?') => {
+        chars.next();
+        let inner = optional_src(&mut chars)?;
+        if !literal.is_empty() {
+          segments.push(Segment::Literal(std::mem::take(&mut literal)));
+        }
+        segments.push(Segment::Optional(parse_segments(&inner, event_type)?));
+      }
+      '{' => {
+        let mut name = String::new();
+        loop {
+          match chars.next() {
+            Some('}') => break,
+            Some(ch) => name.push(ch),
+            None => return Err(format!("unclosed placeholder `{{{name}`")),
+          }
+        }
+        let field =
+          Field::from_name(&name).ok_or_else(|| format!("unknown placeholder `{{{name}}}`"))?;
+        if !field.available_for(event_type) {
+          return Err(format!(
+            "placeholder `{{{name}}}` is not available for {}",
+            event_type.unwrap_or("every event type")
+          ));
+        }
+
+        if !literal.is_empty() {
+          segments.push(Segment::Literal(std::mem::take(&mut literal)));
+        }
+        segments.push(Segment::Field(field));
+      }
+      ch => literal.push(ch),
+    }
+  }
+  if !literal.is_empty() {
+    segments.push(Segment::Literal(literal));
+  }
+  Ok(segments)
+}
+
+/// what's in a `{?...}` after the `{?`, up to its closing brace, which is
+/// taken too
+fn optional_src(chars: &mut Peekable<Chars>) -> Result<String, String> {
+  let mut src = String::new();
+  let mut depth = 0;
+  loop {
+    let ch = chars
+      .next()
+      .ok_or_else(|| format!("unclosed optional section `{{?{src}`"))?;
+    match ch {
+      '{' if chars.peek() == Some(&'{') => {
+        chars.next();
+        src.push_str("{{");
+        continue;
+      }
+      '}' if depth == 0 && chars.peek() == Some(&'}') => {
+        chars.next();
+        src.push_str("}}");
+        continue;
+      }
+      '}' if depth == 0 => return Ok(src),
+      '{' => depth += 1,
+      '}' => depth -= 1,
+      _ => {}
+    }
+    src.push(ch);
+  }
+}
+
+/// an event with the optional fields `event_type` carries filled in
+fn sample_event(event_type: Option<&str>) -> Event {
+  let mut event = Event::default();
+  let data = &mut event.event_data;
+  if Field::RelativePath.available_for(event_type) {
+    data.relative_path = Some(String::new());
+  }
+  if Field::FileSize.available_for(event_type) {
+    data.file_size = Some(0);
+  }
+  if Field::Duration.available_for(event_type) {
+    data.duration = Some(0.0);
+  }
+  event
+}
+
+fn render_simple(segments: &[Segment], event: &Event, json: bool) -> String {
+  let data = &event.event_data;
+  let mut out = String::new();
+  for segment in segments {
+    match segment {
+      Segment::Literal(it) => out.push_str(it),
+      Segment::Optional(inner) => {
+        let filled = inner.iter().all(|it| match it {
+          Segment::Field(field) => !field.is_empty(event),
+          _ => true,
+        });
+        if filled {
+          out += &render_simple(inner, event, json);
+        }
+      }
+      Segment::Field(field) => {
+        let text: Cow<str> = match field {
+          Field::Name => data.name.as_str().into(),
+          Field::Title if json => data.title.as_str().into(),
+          Field::Title => truncate(&data.title, MAX_TITLE_CHARS),
+          Field::RoomId => data.room_id.to_string().into(),
+          Field::ShortId => data.short_id.to_string().into(),
+          Field::AreaParent => data.area_name_parent.as_str().into(),
+          Field::AreaChild => data.area_name_child.as_str().into(),
+          Field::Time => format_time(&event.event_timestamp).into(),
+          Field::RelativePath => data.relative_path.as_deref().unwrap_or("").into(),
+          Field::FileSize => format_size(data.file_size.unwrap_or(0)).into(),
+          Field::Duration => format_duration(data.duration.unwrap_or(0.0)).into(),
+        };
+        match json {
+          true => out += &json_escape(&text),
+          false => out += &text,
+        }
+      }
+    }
+  }
+  out
+}
+
+/// the event timestamp in local time, or as sent if it isn't RFC 3339
+fn format_time(timestamp: &str) -> String {
+  DateTime::parse_from_rfc3339(timestamp)
+    .map(|it| {
+      it.with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+    })
+    .unwrap_or_else(|_| timestamp.to_string())
+}
+
+/// cut `text` to at most `max` chars, the last one being an ellipsis if cut
+pub fn truncate(text: &str, max: usize) -> Cow<'_, str> {
+  match text.char_indices().nth(max) {
+    Some(_) => {
+      let end = text
+        .char_indices()
+        .nth(max.saturating_sub(1))
+        .map_or(0, |(idx, _)| idx);
+      Cow::Owned(format!("{}…", &text[..end]))
+    }
+    None => Cow::Borrowed(text),
+  }
+}
+
+fn format_size(bytes: u64) -> String {
+  static UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+
+  let mut size = bytes as f64;
+  let mut unit = 0;
+  while size >= 1024.0 && unit < UNITS.len() - 1 {
+    size /= 1024.0;
+    unit += 1;
+  }
+  if unit == 0 {
+    format!("{bytes} B")
+  } else {
+    format!("{size:.1} {}", UNITS[unit])
+  }
+}
+
+fn format_duration(secs: f64) -> String {
+  let secs = secs as u64;
+  format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn event(title: &str) -> Event {
+    let mut event = Event::default();
+    event.event_data.name = "a \"quoted\" name".to_string();
+    event.event_data.title = title.to_string();
+    event
+  }
+
+  #[test]
+  fn json_values_are_escaped_whole() {
+    // the escaped quote would be cut in half where titles are cut
+    let title = format!("{}\"\\ end", "x".repeat(MAX_TITLE_CHARS - 2));
+    let event = event(&title);
+    for engine in [Engine::Simple, Engine::Handlebars] {
+      let src = match engine {
+        Engine::Simple => r#"{{"name": "{name}", "title": "{title}"}}"#,
+        Engine::Handlebars => r#"{"name": "{{Name}}", "title": "{{Title}}"}"#,
+      };
+      let template = Template::parse_with(engine, src, None).unwrap();
+      let rendered = template.render_json(&event);
+      let json = serde_json::from_str::<serde_json::Value>(&rendered).unwrap();
+      assert_eq!(json["name"], "a \"quoted\" name");
+      assert_eq!(json["title"], title);
+    }
+  }
+
+  #[test]
+  fn cjk_is_cut_on_char_boundaries() {
+    let title = "测试直播间の标题".repeat(50);
+    let cut = truncate(&title, 200);
+    assert_eq!(cut.chars().count(), 200);
+    assert!(cut.ends_with('…'));
+    let kept = cut.strip_suffix('…').unwrap();
+    assert_eq!(kept, title.chars().take(199).collect::<String>());
+
+    assert!(matches!(truncate("测试", 2), Cow::Borrowed("测试")));
+    assert_eq!(truncate("测试标题", 3), "测试…");
+  }
+}
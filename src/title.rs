@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::Event;
+
+/// the title of each live room as of its last event, for --notify-title-change
+pub struct TitleWatch {
+  /// with when the room's title change was last notified
+  titles: Mutex<HashMap<i64, (String, Option<Instant>)>>,
+  /// between the notified changes of a room, later ones only update the title
+  cooldown: Duration,
+}
+
+impl TitleWatch {
+  pub fn new(cooldown: Duration) -> Self {
+    Self {
+      titles: Default::default(),
+      cooldown,
+    }
+  }
+
+  /// the previous title when the event changed it mid-stream, a start only
+  /// says how the stream begins and an end forgets the room
+  pub fn changed(&self, event: &Event) -> Option<String> {
+    let data = &event.event_data;
+    match event.event_type.as_str() {
+      "StreamStarted" => {
+        let title = (data.title.clone(), None);
+        self.titles.lock().unwrap().insert(data.room_id, title);
+        None
+      }
+      "StreamEnded" => {
+        self.titles.lock().unwrap().remove(&data.room_id);
+        None
+      }
+      _ if data.streaming => self.retitled(data.room_id, &data.title),
+      _ => None,
+    }
+  }
+
+  /// the previous title when `title` differs from it and the room's cooldown
+  /// is over, a room seen for the first time only has its title taken in
+  pub fn retitled(&self, room_id: i64, title: &str) -> Option<String> {
+    let mut titles = self.titles.lock().unwrap();
+    let Some((last, notified)) = titles.get_mut(&room_id) else {
+      titles.insert(room_id, (title.to_string(), None));
+      return None;
+    };
+    if last == title {
+      return None;
+    }
+    let previous = std::mem::replace(last, title.to_string());
+    if notified.is_some_and(|it| it.elapsed() < self.cooldown) {
+      return None;
+    }
+    *notified = Some(Instant::now());
+    Some(previous)
+  }
+}
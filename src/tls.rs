@@ -0,0 +1,17 @@
+use std::sync::Arc;
+
+use tokio_rustls::rustls::{ClientConfig, OwnedTrustAnchor, RootCertStore};
+use tokio_rustls::TlsConnector;
+
+/// for connections reqwest doesn't make, trusting the same roots it does
+pub fn connector() -> TlsConnector {
+  let mut roots = RootCertStore::empty();
+  roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|it| {
+    OwnedTrustAnchor::from_subject_spki_name_constraints(it.subject, it.spki, it.name_constraints)
+  }));
+  let config = ClientConfig::builder()
+    .with_safe_defaults()
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+  TlsConnector::from(Arc::new(config))
+}
@@ -0,0 +1,132 @@
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use chrono::Local;
+use tokio::sync::Notify;
+use tracing::{error, info};
+
+use crate::opener;
+use crate::state::AppState;
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod sni;
+#[cfg(target_os = "windows")]
+mod win32;
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub use sni::start;
+#[cfg(target_os = "windows")]
+pub use win32::{start, stop};
+
+static TITLE: &str = "BiliRecNotifier";
+
+/// events listed in the menu
+static RECENT: usize = 10;
+static MUTE_FOR: Duration = Duration::from_secs(60 * 60);
+/// how often the icon and the menu are brought up to date
+static REFRESH: Duration = Duration::from_secs(2);
+
+// the menu's item ids, the status lines from STATUS_ID and the recent events
+// from RECENT_ID on
+const STATUS_ID: i32 = 1;
+const NO_EVENTS_ID: i32 = 10;
+const MUTE_ID: i32 = 11;
+const QUIT_ID: i32 = 12;
+const RECENT_ID: i32 = 100;
+
+fn quit() -> &'static Notify {
+  static QUIT: OnceLock<Notify> = OnceLock::new();
+  QUIT.get_or_init(Notify::new)
+}
+
+/// Quit picked from the tray's menu, never without --tray
+pub async fn quit_requested() {
+  quit().notified().await;
+}
+
+/// what the icon and the menu show
+#[derive(Clone, PartialEq)]
+struct Shown {
+  /// no webhook for --watchdog-secs
+  silent: bool,
+  muted: bool,
+  /// when the last webhook came, and till when it's muted
+  lines: Vec<String>,
+  /// (label, room id) newest first
+  recent: Vec<(String, i64)>,
+}
+
+fn shown(state: &AppState, silence: Option<Duration>) -> Shown {
+  let lang = state.config_source.lang;
+  let recent = state.event_log.recent(RECENT);
+  let silent = silence.is_some_and(|it| state.last_event.lock().unwrap().elapsed() >= it);
+  let muted = state.quiet.as_ref().and_then(|it| it.muted());
+
+  let mut lines = vec![lang.tray_status(recent.first().map(|(at, _)| *at), silent)];
+  if let Some(until) = muted {
+    let left = until.saturating_duration_since(Instant::now());
+    let until = Local::now() + chrono::Duration::from_std(left).unwrap_or_default();
+    lines.push(lang.tray_muted(until));
+  }
+
+  let recent = recent
+    .into_iter()
+    .map(|(at, event)| {
+      let data = &event.event_data;
+      let name = match data.name.trim().is_empty() {
+        true => data.room_id.to_string(),
+        false => data.name.clone(),
+      };
+      let label = format!("{} {name} {}", at.format("%H:%M"), event.event_type);
+      (label, data.room_id)
+    })
+    .collect();
+
+  Shown {
+    silent,
+    muted: muted.is_some(),
+    lines,
+    recent,
+  }
+}
+
+/// the menu item `id` of `shown` picked, on the runtime, returns whether the
+/// icon and the menu should be brought up to date
+fn clicked(state: &AppState, shown: &Shown, id: i32) -> bool {
+  match id {
+    MUTE_ID => {
+      let Some(quiet) = &state.quiet else {
+        return false;
+      };
+      match quiet.muted() {
+        Some(_) => {
+          quiet.mute(None);
+          info!("unmuted from the tray");
+        }
+        None => {
+          quiet.mute(Some(MUTE_FOR));
+          info!("muted for {MUTE_FOR:?} from the tray");
+        }
+      }
+      true
+    }
+    QUIT_ID => {
+      info!("quitting from the tray");
+      quit().notify_one();
+      false
+    }
+    id if id >= RECENT_ID => {
+      let Some((_, room_id)) = shown.recent.get((id - RECENT_ID) as usize) else {
+        return false;
+      };
+      let url = opener::room_url(*room_id);
+      let opener = state.auto_open.as_ref().and_then(|it| it.opener.clone());
+      tokio::task::spawn_blocking(move || match opener::open(&url, opener.as_deref()) {
+        Ok(()) => info!("opened {url}"),
+        Err(err) => error!("failed to open {url}\n{err:#?}"),
+      });
+      false
+    }
+    _ => false,
+  }
+}
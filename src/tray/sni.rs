@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::runtime::Handle;
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, StructureBuilder, Value};
+use zbus::{dbus_interface, Connection, ConnectionBuilder, SignalContext};
+
+use super::{shown, Shown, MUTE_ID, NO_EVENTS_ID, QUIT_ID, RECENT_ID, REFRESH, STATUS_ID, TITLE};
+use crate::state::AppState;
+
+static ITEM_PATH: &str = "/StatusNotifierItem";
+static MENU_PATH: &str = "/MenuBar";
+static WATCHER: &str = "org.kde.StatusNotifierWatcher";
+static WATCHER_PATH: &str = "/StatusNotifierWatcher";
+
+// the root of the menu, and the ids of its separators from SEPARATOR_ID on
+const ROOT_ID: i32 = 0;
+const SEPARATOR_ID: i32 = 20;
+
+/// an icon in the system tray through StatusNotifierItem, with a menu of the
+/// recent events, a mute for an hour and quit, kept up to date until exit, the
+/// tray is looked for again every [`REFRESH`] while there's none
+pub async fn start(state: Arc<AppState>, silence: Option<Duration>) -> Result<(), String> {
+  let tray = Arc::new(Tray {
+    shown: Mutex::new(shown(&state, silence)),
+    state,
+    silence,
+    revision: AtomicU32::new(1),
+    changed: Notify::new(),
+    runtime: Handle::current(),
+  });
+  let name = format!("org.kde.StatusNotifierItem-{}-1", std::process::id());
+  let conn = ConnectionBuilder::session()
+    .and_then(|it| it.name(name.as_str()))
+    .and_then(|it| it.serve_at(ITEM_PATH, Item(tray.clone())))
+    .and_then(|it| it.serve_at(MENU_PATH, Menu(tray.clone())))
+    .map_err(|err| format!("failed to connect to the session bus\n{err}"))?
+    .build()
+    .await
+    .map_err(|err| format!("failed to connect to the session bus\n{err}"))?;
+  tokio::spawn(refresh(tray, conn, name));
+  Ok(())
+}
+
+struct Tray {
+  state: Arc<AppState>,
+  /// --watchdog-secs
+  silence: Option<Duration>,
+  /// as last told to the tray, what menu item ids stand for
+  shown: Mutex<Shown>,
+  /// of the menu's layout, bumped when it changes
+  revision: AtomicU32,
+  /// wakes [`refresh`] after a click changed something
+  changed: Notify,
+  /// zbus calls the interfaces from a thread of its own, clicks are handled
+  /// on this
+  runtime: Handle,
+}
+
+/// (status, icon name) of the item
+fn look(shown: &Shown) -> (&'static str, &'static str) {
+  match (shown.muted, shown.silent, shown.recent.is_empty()) {
+    (_, true, _) => ("NeedsAttention", "network-offline"),
+    (true, _, _) => ("Active", "notifications-disabled"),
+    (_, _, true) => ("Active", "network-idle"),
+    _ => ("Active", "network-transmit-receive"),
+  }
+}
+
+/// registers with the tray whenever it (re)appears, and tells it of changes
+async fn refresh(tray: Arc<Tray>, conn: Connection, name: String) {
+  let mut registered_with = None;
+  let mut warned = false;
+  loop {
+    let watcher = watcher_owner(&conn).await;
+    if watcher.is_some() && watcher != registered_with {
+      match register(&conn, &name).await {
+        Ok(()) => {
+          info!("tray icon shown");
+          registered_with = watcher;
+        }
+        Err(err) => error!("failed to show the tray icon\n{err}"),
+      }
+    } else if watcher.is_none() && !warned {
+      warn!("no system tray to show the icon in, waiting for one");
+      warned = true;
+      registered_with = None;
+    }
+
+    let shown = shown(&tray.state, tray.silence);
+    let before = std::mem::replace(&mut *tray.shown.lock().unwrap(), shown.clone());
+    if before != shown {
+      if let Err(err) = emit_changes(&tray, &conn, &before, &shown).await {
+        warn!("failed to update the tray icon\n{err}");
+      }
+    }
+
+    tokio::select! {
+      _ = tokio::time::sleep(REFRESH) => {}
+      _ = tray.changed.notified() => {}
+    }
+  }
+}
+
+async fn watcher_owner(conn: &Connection) -> Option<String> {
+  let reply = conn
+    .call_method(
+      Some("org.freedesktop.DBus"),
+      "/org/freedesktop/DBus",
+      Some("org.freedesktop.DBus"),
+      "GetNameOwner",
+      &(WATCHER,),
+    )
+    .await
+    .ok()?;
+  reply.body::<String>().ok()
+}
+
+async fn register(conn: &Connection, name: &str) -> zbus::Result<()> {
+  conn
+    .call_method(
+      Some(WATCHER),
+      WATCHER_PATH,
+      Some(WATCHER),
+      "RegisterStatusNotifierItem",
+      &(name,),
+    )
+    .await
+    .map(|_| ())
+}
+
+async fn emit_changes(
+  tray: &Tray,
+  conn: &Connection,
+  before: &Shown,
+  shown: &Shown,
+) -> zbus::Result<()> {
+  let item = SignalContext::new(conn, ITEM_PATH)?;
+  let ((status_before, icon_before), (status, icon)) = (look(before), look(shown));
+  if icon_before != icon {
+    Item::new_icon(&item).await?;
+  }
+  if status_before != status {
+    Item::new_status(&item, status).await?;
+  }
+  Item::new_tool_tip(&item).await?;
+  let revision = tray.revision.fetch_add(1, Ordering::Relaxed) + 1;
+  Menu::layout_updated(&SignalContext::new(conn, MENU_PATH)?, revision, ROOT_ID).await
+}
+
+fn props<const N: usize>(props: [(&str, Value<'_>); N]) -> HashMap<String, OwnedValue> {
+  props
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value.into()))
+    .collect()
+}
+
+/// the root's children, (id, properties) in order
+fn items(tray: &Tray, shown: &Shown) -> Vec<(i32, HashMap<String, OwnedValue>)> {
+  let (no_events, mute, quit) = tray.state.config_source.lang.tray_menu();
+  let separator = |id| (id, props([("type", "separator".into())]));
+
+  let mut items = vec![];
+  for (id, line) in (STATUS_ID..).zip(&shown.lines) {
+    let label = line.replace('_', "__");
+    items.push((
+      id,
+      props([("label", label.into()), ("enabled", false.into())]),
+    ));
+  }
+  items.push(separator(SEPARATOR_ID));
+  if shown.recent.is_empty() {
+    items.push((
+      NO_EVENTS_ID,
+      props([("label", no_events.into()), ("enabled", false.into())]),
+    ));
+  }
+  for (id, (label, _)) in (RECENT_ID..).zip(&shown.recent) {
+    // a single one is taken as the access key
+    let label = label.replace('_', "__");
+    items.push((id, props([("label", label.into())])));
+  }
+  items.push(separator(SEPARATOR_ID + 1));
+  items.push((
+    MUTE_ID,
+    props([
+      ("label", mute.into()),
+      ("toggle-type", "checkmark".into()),
+      ("toggle-state", i32::from(shown.muted).into()),
+    ]),
+  ));
+  items.push((QUIT_ID, props([("label", quit.into())])));
+  items
+}
+
+/// (icon name, icon pixmaps, title, description)
+type ToolTip = (String, Vec<(i32, i32, Vec<u8>)>, String, String);
+
+struct Item(Arc<Tray>);
+
+#[dbus_interface(name = "org.kde.StatusNotifierItem")]
+impl Item {
+  #[dbus_interface(property)]
+  fn category(&self) -> String {
+    "Communications".to_string()
+  }
+
+  #[dbus_interface(property)]
+  fn id(&self) -> String {
+    "bilibili_rec_notifier".to_string()
+  }
+
+  #[dbus_interface(property)]
+  fn title(&self) -> String {
+    TITLE.to_string()
+  }
+
+  #[dbus_interface(property)]
+  fn status(&self) -> String {
+    look(&self.0.shown.lock().unwrap()).0.to_string()
+  }
+
+  #[dbus_interface(property)]
+  fn window_id(&self) -> u32 {
+    0
+  }
+
+  #[dbus_interface(property)]
+  fn icon_name(&self) -> String {
+    look(&self.0.shown.lock().unwrap()).1.to_string()
+  }
+
+  #[dbus_interface(property)]
+  fn attention_icon_name(&self) -> String {
+    "network-offline".to_string()
+  }
+
+  #[dbus_interface(property)]
+  fn tool_tip(&self) -> ToolTip {
+    let shown = self.0.shown.lock().unwrap();
+    (
+      look(&shown).1.to_string(),
+      vec![],
+      TITLE.to_string(),
+      shown.lines.join("\n"),
+    )
+  }
+
+  /// there's nothing but the menu, which the tray shows on any click then
+  #[dbus_interface(property)]
+  fn item_is_menu(&self) -> bool {
+    true
+  }
+
+  #[dbus_interface(property)]
+  fn menu(&self) -> OwnedObjectPath {
+    OwnedObjectPath::try_from(MENU_PATH).unwrap()
+  }
+
+  fn activate(&self, _x: i32, _y: i32) {}
+
+  fn secondary_activate(&self, _x: i32, _y: i32) {}
+
+  fn context_menu(&self, _x: i32, _y: i32) {}
+
+  fn scroll(&self, _delta: i32, _orientation: String) {}
+
+  #[dbus_interface(signal)]
+  async fn new_icon(ctxt: &SignalContext<'_>) -> zbus::Result<()>;
+
+  #[dbus_interface(signal)]
+  async fn new_status(ctxt: &SignalContext<'_>, status: &str) -> zbus::Result<()>;
+
+  #[dbus_interface(signal)]
+  async fn new_tool_tip(ctxt: &SignalContext<'_>) -> zbus::Result<()>;
+}
+
+type Layout = (i32, HashMap<String, OwnedValue>, Vec<OwnedValue>);
+
+struct Menu(Arc<Tray>);
+
+impl Menu {
+  /// on the runtime, or the log lines would be in zbus' spans
+  fn clicked(&self, id: i32) {
+    let tray = self.0.clone();
+    self.0.runtime.spawn(async move {
+      let shown = tray.shown.lock().unwrap().clone();
+      if super::clicked(&tray.state, &shown, id) {
+        tray.changed.notify_one();
+      }
+    });
+  }
+}
+
+/// the menu as the tray fetches it, one level below the root
+#[dbus_interface(name = "com.canonical.dbusmenu")]
+impl Menu {
+  #[dbus_interface(property)]
+  fn version(&self) -> u32 {
+    3
+  }
+
+  #[dbus_interface(property)]
+  fn text_direction(&self) -> String {
+    "ltr".to_string()
+  }
+
+  #[dbus_interface(property)]
+  fn status(&self) -> String {
+    "normal".to_string()
+  }
+
+  #[dbus_interface(property)]
+  fn icon_theme_path(&self) -> Vec<String> {
+    vec![]
+  }
+
+  fn get_layout(
+    &self,
+    parent_id: i32,
+    _recursion_depth: i32,
+    _property_names: Vec<String>,
+  ) -> (u32, Layout) {
+    let revision = self.0.revision.load(Ordering::Relaxed);
+    if parent_id != ROOT_ID {
+      return (revision, (parent_id, HashMap::new(), vec![]));
+    }
+    let shown = self.0.shown.lock().unwrap().clone();
+    let children = items(&self.0, &shown)
+      .into_iter()
+      .map(|(id, props)| {
+        let child = StructureBuilder::new()
+          .add_field(id)
+          .add_field(props)
+          .add_field(Vec::<OwnedValue>::new())
+          .build();
+        Value::from(child).into()
+      })
+      .collect();
+    let root = props([("children-display", "submenu".into())]);
+    (revision, (ROOT_ID, root, children))
+  }
+
+  fn get_group_properties(
+    &self,
+    ids: Vec<i32>,
+    _property_names: Vec<String>,
+  ) -> Vec<(i32, HashMap<String, OwnedValue>)> {
+    let shown = self.0.shown.lock().unwrap().clone();
+    let mut items = items(&self.0, &shown);
+    items.push((ROOT_ID, props([("children-display", "submenu".into())])));
+    items.retain(|(id, _)| ids.is_empty() || ids.contains(id));
+    items
+  }
+
+  fn get_property(&self, id: i32, name: String) -> zbus::fdo::Result<OwnedValue> {
+    let shown = self.0.shown.lock().unwrap().clone();
+    items(&self.0, &shown)
+      .into_iter()
+      .find(|(it, _)| *it == id)
+      .and_then(|(_, mut props)| props.remove(&name))
+      .ok_or_else(|| zbus::fdo::Error::InvalidArgs(format!("no {name} of item {id}")))
+  }
+
+  fn event(&self, id: i32, event_id: String, _data: OwnedValue, _timestamp: u32) {
+    if event_id == "clicked" {
+      self.clicked(id);
+    }
+  }
+
+  fn event_group(&self, events: Vec<(i32, String, OwnedValue, u32)>) -> Vec<i32> {
+    for (id, event_id, _, _) in events {
+      if event_id == "clicked" {
+        self.clicked(id);
+      }
+    }
+    vec![]
+  }
+
+  /// the menu is kept up to date already
+  fn about_to_show(&self, _id: i32) -> bool {
+    false
+  }
+
+  fn about_to_show_group(&self, _ids: Vec<i32>) -> (Vec<i32>, Vec<i32>) {
+    (vec![], vec![])
+  }
+
+  #[dbus_interface(signal)]
+  async fn layout_updated(ctxt: &SignalContext<'_>, revision: u32, parent: i32)
+    -> zbus::Result<()>;
+}
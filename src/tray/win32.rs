@@ -0,0 +1,294 @@
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use tokio::runtime::Handle;
+use tokio::sync::oneshot;
+use tracing::{info, warn};
+use windows::core::{HSTRING, PCWSTR};
+use windows::Win32::Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, POINT, WPARAM};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::Shell::{
+  Shell_NotifyIconW, NIF_ICON, NIF_MESSAGE, NIF_TIP, NIM_ADD, NIM_DELETE, NIM_MODIFY,
+  NOTIFYICONDATAW,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+  AppendMenuW, CreatePopupMenu, CreateWindowExW, DefWindowProcW, DestroyMenu, DispatchMessageW,
+  GetCursorPos, GetMessageW, LoadIconW, PostQuitMessage, RegisterClassW, RegisterWindowMessageW,
+  SendMessageW, SetForegroundWindow, SetTimer, TrackPopupMenu, TranslateMessage, IDI_APPLICATION,
+  IDI_INFORMATION, IDI_WARNING, MENU_ITEM_FLAGS, MF_CHECKED, MF_GRAYED, MF_SEPARATOR, MF_STRING,
+  MSG, TPM_NONOTIFY, TPM_RETURNCMD, TPM_RIGHTBUTTON, WINDOW_EX_STYLE, WM_APP, WM_CLOSE, WM_DESTROY,
+  WM_LBUTTONUP, WM_RBUTTONUP, WM_TIMER, WNDCLASSW, WS_OVERLAPPED,
+};
+
+use super::{shown, Shown, MUTE_ID, NO_EVENTS_ID, QUIT_ID, RECENT_ID, REFRESH, STATUS_ID, TITLE};
+use crate::state::AppState;
+
+/// of the window the icon's messages go to, which is never shown
+static CLASS: &str = "BiliRecNotifierTray";
+/// sent by explorer when the taskbar is (re)created, after it restarted
+static TASKBAR_CREATED: &str = "TaskbarCreated";
+
+/// what the icon sends on mouse events, with the event as the lparam
+const ICON_MESSAGE: u32 = WM_APP + 1;
+const ICON_ID: u32 = 1;
+const REFRESH_TIMER: usize = 1;
+
+/// for [`window_proc`], which windows calls without it
+static TRAY: OnceLock<Tray> = OnceLock::new();
+
+struct Tray {
+  state: Arc<AppState>,
+  /// --watchdog-secs
+  silence: Option<Duration>,
+  icon: Mutex<Icon>,
+  /// the window's thread has none, clicks are handled on this
+  runtime: Handle,
+  /// the message [`TASKBAR_CREATED`] is registered as
+  taskbar_created: u32,
+  window: OnceLock<HWND>,
+}
+
+struct Icon {
+  /// to the notification area, it's added again after explorer restarts
+  added: bool,
+  /// that there's no notification area to add it to
+  warned: bool,
+  /// as last put in the icon's tip, what menu item ids stand for
+  shown: Shown,
+}
+
+/// an icon in the notification area through Shell_NotifyIconW, with a menu of
+/// the recent events, a mute for an hour and quit, kept up to date until
+/// [`stop`], from a hidden window on a thread of its own
+pub async fn start(state: Arc<AppState>, silence: Option<Duration>) -> Result<(), String> {
+  let taskbar_created = unsafe { RegisterWindowMessageW(&HSTRING::from(TASKBAR_CREATED)) };
+  let tray = Tray {
+    icon: Mutex::new(Icon {
+      added: false,
+      warned: false,
+      shown: shown(&state, silence),
+    }),
+    state,
+    silence,
+    runtime: Handle::current(),
+    taskbar_created,
+    window: OnceLock::new(),
+  };
+  if TRAY.set(tray).is_err() {
+    return Err("the tray is already shown".to_string());
+  }
+
+  let (created, window) = oneshot::channel();
+  std::thread::spawn(move || {
+    // the window belongs to the thread that creates it, only it gets its
+    // messages
+    let window = unsafe { create_window() };
+    let ok = window.is_ok();
+    let _ = created.send(window);
+    if ok {
+      unsafe { run() };
+    }
+  });
+  let window = window
+    .await
+    .map_err(|_| "the tray's thread is gone".to_string())??;
+  let tray = TRAY.get().unwrap();
+  let _ = tray.window.set(window);
+  unsafe { SendMessageW(window, WM_TIMER, WPARAM(REFRESH_TIMER), LPARAM(0)) };
+  Ok(())
+}
+
+/// remove the icon, which would otherwise stay until the mouse passes over it
+pub fn stop() {
+  let Some(window) = TRAY.get().and_then(|it| it.window.get()) else {
+    return;
+  };
+  // waits for the window's thread to handle it
+  unsafe { SendMessageW(*window, WM_CLOSE, WPARAM(0), LPARAM(0)) };
+}
+
+unsafe fn create_window() -> Result<HWND, String> {
+  let instance = GetModuleHandleW(PCWSTR::null())
+    .map_err(|err| format!("failed to get the module handle\n{err}"))?;
+  let class = HSTRING::from(CLASS);
+  let window_class = WNDCLASSW {
+    lpfnWndProc: Some(window_proc),
+    hInstance: instance,
+    lpszClassName: PCWSTR(class.as_ptr()),
+    ..Default::default()
+  };
+  if RegisterClassW(&window_class) == 0 {
+    return Err(format!(
+      "failed to register the window class\n{}",
+      windows::core::Error::from_win32()
+    ));
+  }
+  let window = CreateWindowExW(
+    WINDOW_EX_STYLE::default(),
+    &class,
+    &HSTRING::from(TITLE),
+    WS_OVERLAPPED,
+    0,
+    0,
+    0,
+    0,
+    None,
+    None,
+    instance,
+    std::ptr::null(),
+  );
+  if window.0 == 0 {
+    return Err(format!(
+      "failed to create the window\n{}",
+      windows::core::Error::from_win32()
+    ));
+  }
+  SetTimer(window, REFRESH_TIMER, REFRESH.as_millis() as u32, None);
+  Ok(window)
+}
+
+/// the window's messages until it's destroyed
+unsafe fn run() {
+  let mut message = MSG::default();
+  while GetMessageW(&mut message, None, 0, 0).as_bool() {
+    TranslateMessage(&message);
+    DispatchMessageW(&message);
+  }
+}
+
+unsafe extern "system" fn window_proc(
+  window: HWND,
+  message: u32,
+  wparam: WPARAM,
+  lparam: LPARAM,
+) -> LRESULT {
+  let Some(tray) = TRAY.get() else {
+    return DefWindowProcW(window, message, wparam, lparam);
+  };
+  match message {
+    WM_TIMER => refresh(tray, window),
+    ICON_MESSAGE if matches!(lparam.0 as u32, WM_LBUTTONUP | WM_RBUTTONUP) => {
+      menu(tray, window);
+    }
+    WM_DESTROY => {
+      let data = icon_data(window, &tray.icon.lock().unwrap().shown);
+      Shell_NotifyIconW(NIM_DELETE, &data);
+      PostQuitMessage(0);
+    }
+    _ if message == tray.taskbar_created => {
+      tray.icon.lock().unwrap().added = false;
+      refresh(tray, window);
+    }
+    _ => return DefWindowProcW(window, message, wparam, lparam),
+  }
+  LRESULT(0)
+}
+
+/// adds the icon when it isn't yet, and brings it up to date
+unsafe fn refresh(tray: &Tray, window: HWND) {
+  let shown = shown(&tray.state, tray.silence);
+  let mut icon = tray.icon.lock().unwrap();
+  if icon.added && icon.shown == shown {
+    return;
+  }
+  let data = icon_data(window, &shown);
+  if !icon.added {
+    icon.added = Shell_NotifyIconW(NIM_ADD, &data).as_bool();
+    if icon.added {
+      info!("tray icon shown");
+      icon.warned = false;
+    } else if !icon.warned {
+      warn!("no notification area to show the icon in, waiting for one");
+      icon.warned = true;
+    }
+  } else if !Shell_NotifyIconW(NIM_MODIFY, &data).as_bool() {
+    warn!("failed to update the tray icon");
+  }
+  icon.shown = shown;
+}
+
+unsafe fn icon_data(window: HWND, shown: &Shown) -> NOTIFYICONDATAW {
+  let icon = match (shown.silent, shown.muted) {
+    (true, _) => PCWSTR(IDI_WARNING as _),
+    (_, true) => PCWSTR(IDI_INFORMATION as _),
+    _ => IDI_APPLICATION,
+  };
+  let mut data = NOTIFYICONDATAW {
+    cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+    hWnd: window,
+    uID: ICON_ID,
+    uFlags: NIF_ICON | NIF_MESSAGE | NIF_TIP,
+    uCallbackMessage: ICON_MESSAGE,
+    hIcon: LoadIconW(HINSTANCE(0), icon).unwrap_or_default(),
+    ..Default::default()
+  };
+  // cut to fit, leaving the terminating 0
+  let tip = [TITLE.to_string()]
+    .iter()
+    .chain(&shown.lines)
+    .map(String::as_str)
+    .collect::<Vec<_>>()
+    .join("\n");
+  let room = data.szTip.len() - 1;
+  for (to, from) in data.szTip.iter_mut().zip(tip.encode_utf16().take(room)) {
+    *to = from;
+  }
+  data
+}
+
+/// the menu at the cursor, what's picked from it is handled before it returns
+unsafe fn menu(tray: &Tray, window: HWND) {
+  let shown = tray.icon.lock().unwrap().shown.clone();
+  let Ok(menu) = CreatePopupMenu() else {
+    warn!("failed to show the tray menu");
+    return;
+  };
+  let (no_events, mute, quit) = tray.state.config_source.lang.tray_menu();
+  // a single & is taken as the access key
+  let item = |flags: MENU_ITEM_FLAGS, id: i32, label: &str| {
+    let label = HSTRING::from(label.replace('&', "&&"));
+    AppendMenuW(menu, MF_STRING | flags, id as usize, &label);
+  };
+  let separator = || AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null());
+
+  for (id, line) in (STATUS_ID..).zip(&shown.lines) {
+    item(MF_GRAYED, id, line);
+  }
+  separator();
+  if shown.recent.is_empty() {
+    item(MF_GRAYED, NO_EVENTS_ID, no_events);
+  }
+  for (id, (label, _)) in (RECENT_ID..).zip(&shown.recent) {
+    item(MENU_ITEM_FLAGS::default(), id, label);
+  }
+  separator();
+  let checked = match shown.muted {
+    true => MF_CHECKED,
+    false => MENU_ITEM_FLAGS::default(),
+  };
+  item(checked, MUTE_ID, mute);
+  item(MENU_ITEM_FLAGS::default(), QUIT_ID, quit);
+
+  let mut cursor = POINT::default();
+  GetCursorPos(&mut cursor);
+  // or the menu stays open when clicking elsewhere
+  SetForegroundWindow(window);
+  let picked = TrackPopupMenu(
+    menu,
+    TPM_RETURNCMD | TPM_NONOTIFY | TPM_RIGHTBUTTON,
+    cursor.x,
+    cursor.y,
+    0,
+    window,
+    std::ptr::null(),
+  );
+  DestroyMenu(menu);
+
+  // 0 when nothing is
+  if picked.0 != 0 {
+    let _runtime = tray.runtime.enter();
+    if super::clicked(&tray.state, &shown, picked.0) {
+      refresh(tray, window);
+    }
+  }
+}
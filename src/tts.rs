@@ -0,0 +1,116 @@
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tokio::process::Command;
+use tracing::{error, info};
+
+use crate::i18n::Lang;
+use crate::template::Template;
+use crate::Event;
+
+/// speaks a short phrase per notification with the platform's speech program,
+/// the text is passed as a single argument (or environment variable on
+/// windows), never through a shell
+pub struct Tts {
+  /// the phrase for every event type, the language's own otherwise
+  template: Option<Template>,
+  lang: Lang,
+  /// set after the first failure, so it's reported once
+  failed: AtomicBool,
+}
+
+impl Tts {
+  pub fn new(template: Option<Template>, lang: Lang) -> Self {
+    Self {
+      template,
+      lang,
+      failed: AtomicBool::new(false),
+    }
+  }
+
+  pub fn speak(&self, event: &Event) {
+    if self.failed.load(Ordering::Relaxed) {
+      return;
+    }
+
+    let text = match &self.template {
+      Some(template) => template.render(event),
+      None => Template::parse(self.lang.spoken(&event.event_type), None)
+        .map(|it| it.render(event))
+        .unwrap_or_default(),
+    };
+    // so it can't be taken for an option
+    let text = text.trim_start_matches('-').trim().to_string();
+    if text.is_empty() {
+      return;
+    }
+
+    let mut spawned = Err(None);
+    for mut command in speech_commands(&text) {
+      match command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+      {
+        Ok(child) => {
+          spawned = Ok(child);
+          break;
+        }
+        Err(err) => spawned = Err(Some(err)),
+      }
+    }
+    let mut child = match spawned {
+      Ok(it) => it,
+      Err(err) => {
+        self.failed.store(true, Ordering::Relaxed);
+        error!("failed to run text to speech, turning it off\n{err:#?}");
+        return;
+      }
+    };
+    tokio::spawn(async move {
+      match child.wait().await {
+        Ok(status) if status.success() => info!("spoken"),
+        Ok(status) => error!("text to speech exited with {status}"),
+        Err(err) => error!("failed to wait for text to speech\n{err:#?}"),
+      }
+    });
+  }
+}
+
+/// the programs to try in order
+#[cfg(target_os = "macos")]
+fn speech_commands(text: &str) -> Vec<Command> {
+  let mut command = Command::new("say");
+  command.arg(text);
+  vec![command]
+}
+
+/// the programs to try in order, speech dispatcher first as it uses the voice
+/// the user set up
+#[cfg(all(unix, not(target_os = "macos")))]
+fn speech_commands(text: &str) -> Vec<Command> {
+  ["spd-say", "espeak"]
+    .into_iter()
+    .map(|program| {
+      let mut command = Command::new(program);
+      command.arg(text);
+      command
+    })
+    .collect()
+}
+
+/// the programs to try in order
+#[cfg(target_os = "windows")]
+fn speech_commands(text: &str) -> Vec<Command> {
+  let mut command = Command::new("powershell");
+  command
+    .args([
+      "-NoProfile",
+      "-Command",
+      "Add-Type -AssemblyName System.Speech; \
+       (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak($env:BILI_TTS_TEXT)",
+    ])
+    .env("BILI_TTS_TEXT", text);
+  vec![command]
+}
@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset};
+
+/// streams running longer are taken as ones whose end never came, and rooms
+/// offline longer don't have their end kept
+pub static MAX_STREAM: Duration = Duration::from_secs(2 * 24 * 3600);
+
+/// when each live room's stream started, to say how long it ran once it ends,
+/// and when each room's last stream ended, to tell a restream from a start
+#[derive(Default)]
+pub struct StreamStarts {
+  started: Mutex<HashMap<i64, Started>>,
+  ended: Mutex<HashMap<i64, DateTime<FixedOffset>>>,
+}
+
+struct Started {
+  at: DateTime<FixedOffset>,
+  /// how long the room was offline before, when its end was seen
+  gap: Option<Duration>,
+}
+
+fn recent(at: DateTime<FixedOffset>, then: DateTime<FixedOffset>) -> bool {
+  (at - then).to_std().is_ok_and(|it| it < MAX_STREAM)
+}
+
+impl StreamStarts {
+  pub fn start(&self, room_id: i64, at: DateTime<FixedOffset>) {
+    let mut ended = self.ended.lock().unwrap();
+    ended.retain(|_, it| recent(at, *it));
+    let gap = ended
+      .remove(&room_id)
+      .and_then(|it| (at - it).to_std().ok());
+    let mut started = self.started.lock().unwrap();
+    started.retain(|_, it| recent(at, it.at));
+    started.insert(room_id, Started { at, gap });
+  }
+
+  /// how long the room streamed, `None` when its start wasn't seen
+  pub fn end(&self, room_id: i64, at: DateTime<FixedOffset>) -> Option<Duration> {
+    self.ended.lock().unwrap().insert(room_id, at);
+    let started = self.started.lock().unwrap().remove(&room_id)?;
+    (at - started.at).to_std().ok()
+  }
+
+  /// how long the room was offline before its current stream, `None` when
+  /// it's not live or its last end wasn't seen
+  pub fn gap(&self, room_id: i64) -> Option<Duration> {
+    self.started.lock().unwrap().get(&room_id)?.gap
+  }
+}
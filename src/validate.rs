@@ -0,0 +1,57 @@
+use serde_json::{Map, Value};
+
+use crate::Event;
+
+/// parse a webhook body, on failure listing every field that's missing or of
+/// the wrong type instead of only the first one serde runs into
+pub fn validate(body: &[u8]) -> Result<Event, Vec<String>> {
+  let given = serde_json::from_slice::<Value>(body).map_err(|err| vec![err.to_string()])?;
+  let err = match serde_json::from_value::<Event>(given.clone()) {
+    Ok(event) => return Ok(event),
+    Err(err) => err,
+  };
+  let Value::Object(given) = given else {
+    return Err(vec![err.to_string()]);
+  };
+
+  // each given field is tried alone in an otherwise valid event
+  let template = serde_json::to_value(Event::default()).unwrap();
+  let mut errors = vec![];
+  check(&template, &given, &[], &mut errors);
+  if errors.is_empty() {
+    errors.push(err.to_string());
+  }
+  Err(errors)
+}
+
+fn check(template: &Value, given: &Map<String, Value>, path: &[&str], errors: &mut Vec<String>) {
+  let Some(Value::Object(fields)) = template.pointer(&pointer(path)) else {
+    return;
+  };
+  for name in fields.keys().filter(|it| !given.contains_key(*it)) {
+    errors.push(format!("{}: missing field", join(path, name)));
+  }
+  for (name, value) in given {
+    let field = [path, &[name.as_str()]].concat();
+    match (fields.get(name), value) {
+      (Some(Value::Object(_)), Value::Object(given)) => check(template, given, &field, errors),
+      _ => {
+        let mut event = template.clone();
+        if let Some(Value::Object(parent)) = event.pointer_mut(&pointer(path)) {
+          parent.insert(name.clone(), value.clone());
+        }
+        if let Err(err) = serde_json::from_value::<Event>(event) {
+          errors.push(format!("{}: {err}", join(path, name)));
+        }
+      }
+    }
+  }
+}
+
+fn pointer(path: &[&str]) -> String {
+  path.iter().map(|it| format!("/{it}")).collect()
+}
+
+fn join(path: &[&str], name: &str) -> String {
+  [path, &[name]].concat().join(".")
+}
@@ -0,0 +1,33 @@
+//! the build, as build.rs found it
+
+pub static VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// the short hash, `-dirty` with uncommitted changes, `unknown` without git
+pub static COMMIT: &str = env!("BUILD_COMMIT");
+
+pub static TARGET: &str = env!("BUILD_TARGET");
+
+/// comma separated, `default` left out
+pub static FEATURES: &str = env!("BUILD_FEATURES");
+
+/// like `bilibili_rec_notifier 0.1.0 (3f2a9c1d0b7e, x86_64-unknown-linux-gnu, features: desktop-notifications)`
+pub fn line() -> String {
+  let features = match FEATURES.is_empty() {
+    true => "none".to_string(),
+    false => FEATURES.replace(',', ", "),
+  };
+  format!(
+    "{} {VERSION} ({COMMIT}, {TARGET}, features: {features})",
+    env!("CARGO_PKG_NAME")
+  )
+}
+
+/// for /healthz
+pub fn json() -> serde_json::Value {
+  serde_json::json!({
+    "version": VERSION,
+    "commit": COMMIT,
+    "target": TARGET,
+    "features": FEATURES.split(',').filter(|it| !it.is_empty()).collect::<Vec<_>>(),
+  })
+}